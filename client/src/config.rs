@@ -0,0 +1,169 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use anchor_client::{
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        signature::{read_keypair_file, Keypair, Signature},
+    },
+    Cluster,
+};
+use anyhow::{anyhow, Context};
+use clap::Args;
+use solana_program::pubkey::Pubkey;
+
+use crate::{output::OutputFormat, price_source::PriceSourceKind, transaction::PriorityFee};
+
+// Where and as whom to send transactions, and how to report them. Each option falls back
+// to its environment variable (`.env` included), then to `Anchor.toml`.
+#[derive(Args)]
+pub struct ConfigArgs {
+    /// localnet, devnet, mainnet or testnet
+    #[arg(long, global = true, env = "ZONE_CLUSTER")]
+    pub cluster: Option<String>,
+
+    /// RPC URL, instead of the default one of the cluster
+    #[arg(long, global = true, env = "ZONE_URL")]
+    pub url: Option<String>,
+
+    /// Zone program id
+    #[arg(long, global = true, env = "ZONE_PROGRAM_ID")]
+    pub program_id: Option<String>,
+
+    /// Keypair file of the wallet
+    #[arg(long, global = true, env = "ANCHOR_WALLET")]
+    pub keypair: Option<String>,
+
+    /// processed, confirmed or finalized
+    #[arg(
+        long,
+        global = true,
+        env = "ZONE_COMMITMENT",
+        default_value = "confirmed"
+    )]
+    pub commitment: String,
+
+    /// text, or json for a single JSON object on stdout
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    pub output: OutputFormat,
+
+    /// Simulate transactions instead of sending them
+    #[arg(long, global = true)]
+    pub simulate: bool,
+
+    /// Micro-lamports per compute unit, or auto to estimate it from recent fees
+    #[arg(long, global = true, env = "ZONE_PRIORITY_FEE")]
+    pub priority_fee: Option<PriorityFee>,
+
+    /// Compute units the transaction can consume
+    #[arg(long, global = true, env = "ZONE_COMPUTE_LIMIT")]
+    pub compute_limit: Option<u32>,
+
+    /// oracle, an HTTP URL with {token} for the token address, or a file of token prices
+    #[arg(
+        long,
+        global = true,
+        env = "ZONE_PRICE_SOURCE",
+        default_value = "oracle"
+    )]
+    pub price_source: PriceSourceKind,
+}
+
+pub struct Config {
+    pub cluster: Cluster,
+    pub program_id: Pubkey,
+    pub payer: Keypair,
+    pub commitment: CommitmentConfig,
+    pub output: OutputFormat,
+    pub simulate: bool,
+    pub priority_fee: Option<PriorityFee>,
+    pub compute_limit: Option<u32>,
+    pub price_source: PriceSourceKind,
+}
+
+impl Config {
+    pub fn load(args: &ConfigArgs) -> anyhow::Result<Config> {
+        let anchor_toml = find_anchor_toml();
+        let provider = anchor_toml.as_ref().and_then(|toml| toml.get("provider"));
+
+        let cluster_name = args
+            .cluster
+            .clone()
+            .or_else(|| provider?.get("cluster")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| "localnet".to_string());
+        let cluster = match &args.url {
+            Some(url) => {
+                Cluster::from_str(url).with_context(|| format!("Invalid RPC URL: {url}"))?
+            }
+            None => Cluster::from_str(&cluster_name)
+                .with_context(|| format!("Invalid cluster: {cluster_name}"))?,
+        };
+
+        let program_id = args
+            .program_id
+            .clone()
+            .or_else(|| {
+                let programs = anchor_toml.as_ref()?.get("programs")?;
+                let programs = programs.get(cluster_name.to_lowercase())?;
+                programs.get("zone")?.as_str().map(str::to_string)
+            })
+            .map(|program_id| {
+                Pubkey::from_str(&program_id)
+                    .with_context(|| format!("Invalid program id: {program_id}"))
+            })
+            .transpose()?
+            .unwrap_or(zone::ID);
+
+        let keypair = args
+            .keypair
+            .clone()
+            .or_else(|| provider?.get("wallet")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| "~/.config/solana/id.json".to_string());
+        let payer = read_keypair_file(expand_home(&keypair))
+            .map_err(|err| anyhow!("Failed to read keypair {keypair}: {err}"))?;
+
+        let commitment = CommitmentConfig::from_str(&args.commitment)
+            .with_context(|| format!("Invalid commitment level: {}", args.commitment))?;
+
+        Ok(Config {
+            cluster,
+            program_id,
+            payer,
+            commitment,
+            output: args.output,
+            simulate: args.simulate,
+            priority_fee: args.priority_fee,
+            compute_limit: args.compute_limit,
+            price_source: args.price_source.clone(),
+        })
+    }
+
+    /// Solscan link to the transaction on the cluster in use.
+    pub fn explorer_url(&self, sig: &Signature) -> String {
+        match &self.cluster {
+            Cluster::Mainnet => format!("https://solscan.io/tx/{sig}"),
+            Cluster::Devnet => format!("https://solscan.io/tx/{sig}?cluster=devnet"),
+            Cluster::Testnet => format!("https://solscan.io/tx/{sig}?cluster=testnet"),
+            cluster => format!(
+                "https://solscan.io/tx/{sig}?cluster=custom&customUrl={}",
+                cluster.url()
+            ),
+        }
+    }
+}
+
+/// `Anchor.toml` of the workspace the client runs in, if any.
+fn find_anchor_toml() -> Option<toml::Value> {
+    let current_dir = std::env::current_dir().ok()?;
+
+    current_dir.ancestors().find_map(|dir| {
+        let content = fs::read_to_string(dir.join("Anchor.toml")).ok()?;
+        toml::from_str(&content).ok()
+    })
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,208 @@
+use chrono::DateTime;
+use serde_json::{json, Value};
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use zone::{Market, MarketKind, Prediction, PredictionStatus, Price, Vault};
+
+pub fn sol(lamports: u64) -> String {
+    format!("{} SOL", lamports as f64 / LAMPORTS_PER_SOL as f64)
+}
+
+pub fn signed_sol(lamports: i64) -> String {
+    format!("{} SOL", lamports as f64 / LAMPORTS_PER_SOL as f64)
+}
+
+pub fn time(timestamp: i64) -> String {
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+fn limit(lamports: u64) -> String {
+    if lamports == 0 {
+        "none".to_string()
+    } else {
+        sol(lamports)
+    }
+}
+
+/// Title of the market, its token when it has none.
+pub fn market_title(market: &Market) -> String {
+    if market.metadata.title.is_empty() {
+        market.token_account.to_string()
+    } else {
+        market.metadata.title.clone()
+    }
+}
+
+pub fn market_kind(kind: MarketKind) -> &'static str {
+    match kind {
+        MarketKind::HigherLower => "higher or lower",
+        MarketKind::Range => "range",
+        MarketKind::PercentageMove => "percentage move",
+    }
+}
+
+pub fn market_status(market: &Market, now: i64) -> &'static str {
+    if market.finalized {
+        "finalized"
+    } else if market.resolved {
+        "resolved, in dispute period"
+    } else if !market.started {
+        "not started"
+    } else if market.end > now {
+        "open"
+    } else {
+        "ended, not resolved"
+    }
+}
+
+pub fn prediction_status(status: PredictionStatus) -> &'static str {
+    match status {
+        PredictionStatus::Open => "open",
+        PredictionStatus::Settled => "settled",
+        PredictionStatus::CashedOut => "cashed out",
+    }
+}
+
+/// Side the prediction is on, in the terms of its market.
+pub fn prediction_side(prediction: &Prediction, kind: MarketKind) -> String {
+    match (kind, prediction.prediction) {
+        (MarketKind::Range, _) => format!("bucket {}", prediction.bucket),
+        (MarketKind::PercentageMove, true) => "MOVED".to_string(),
+        (MarketKind::PercentageMove, false) => "STAYED".to_string(),
+        (MarketKind::HigherLower, true) => "HIGH".to_string(),
+        (MarketKind::HigherLower, false) => "LOW".to_string(),
+    }
+}
+
+pub fn print_vault(vault_pda: &Pubkey, vault: &Vault) {
+    println!("Vault {} ({vault_pda})", vault.vault_num);
+    println!("  authority:            {}", vault.authority);
+    println!("  deposits:             {}", sol(vault.deposits));
+    println!("  realized P&L:         {}", signed_sol(vault.realized_pnl));
+    println!("  liabilities:          {}", sol(vault.liabilities));
+    println!("  open predictions:     {}", vault.open_predictions);
+    println!("  withdrawal delay:     {} s", vault.withdrawal_delay);
+    println!(
+        "  max market liability: {}",
+        limit(vault.max_market_liability)
+    );
+    println!(
+        "  max total liability:  {}",
+        limit(vault.max_total_liability)
+    );
+    println!("  max imbalance:        {}", limit(vault.max_imbalance));
+}
+
+pub fn print_market(market_pda: &Pubkey, market: &Market, now: i64) {
+    println!("Market {} ({market_pda})", market_title(market));
+    println!("  token:             {}", market.token_account);
+    println!("  authority:         {}", market.authority);
+    println!("  kind:              {}", market_kind(market.kind));
+    println!("  status:            {}", market_status(market, now));
+    if market.started {
+        println!("  start:             {}", time(market.start));
+        println!("  end:               {}", time(market.end));
+    }
+    println!("  payout multiplier: {}", market.payout_multiplier);
+    println!("  HIGH exposure:     {}", sol(market.high_exposure));
+    println!("  LOW exposure:      {}", sol(market.low_exposure));
+    println!("  liability:         {}", sol(market.liability));
+    println!("  open predictions:  {}", market.open_predictions);
+    println!("  oracle:            {}", market.oracle);
+    if market.resolved {
+        println!("  final price:       {}", market.final_price);
+        println!("  resolved at:       {}", time(market.resolved_at));
+    }
+}
+
+/// `market` is none once the market was closed, its kind is unknown then.
+pub fn print_prediction(prediction_pda: &Pubkey, prediction: &Prediction, market: Option<&Market>) {
+    let side = market.map_or_else(
+        || "unknown".to_string(),
+        |market| prediction_side(prediction, market.kind),
+    );
+    let closed = if market.is_none() { " (closed)" } else { "" };
+
+    println!("Prediction {prediction_pda}");
+    println!("  market:      {}{closed}", prediction.market);
+    println!("  user:        {}", prediction.user);
+    println!("  side:        {side}");
+    println!("  entry price: {}", prediction.market_price);
+    println!("  amount:      {}", sol(prediction.amount));
+    println!("  multiplier:  {}", prediction.payout_multiplier);
+    println!("  payout:      {}", sol(prediction.payout()));
+    println!("  status:      {}", prediction_status(prediction.status));
+    println!("  vault:       {}", prediction.vault);
+}
+
+// JSON counterparts of the above for `--output json`. Amounts stay in lamports and times
+// in Unix timestamps, so scripts don't have to parse them back.
+
+pub fn price_json(price: Price) -> Value {
+    json!({
+        "mantissa": price.mantissa,
+        "expo": price.expo,
+        "value": price.to_string(),
+    })
+}
+
+pub fn vault_json(vault_pda: &Pubkey, vault: &Vault) -> Value {
+    json!({
+        "address": vault_pda.to_string(),
+        "vault_num": vault.vault_num,
+        "authority": vault.authority.to_string(),
+        "deposits": vault.deposits,
+        "realized_pnl": vault.realized_pnl,
+        "liabilities": vault.liabilities,
+        "open_predictions": vault.open_predictions,
+        "withdrawal_delay": vault.withdrawal_delay,
+        "max_market_liability": vault.max_market_liability,
+        "max_total_liability": vault.max_total_liability,
+        "max_imbalance": vault.max_imbalance,
+    })
+}
+
+pub fn market_json(market_pda: &Pubkey, market: &Market, now: i64) -> Value {
+    json!({
+        "address": market_pda.to_string(),
+        "token_account": market.token_account.to_string(),
+        "authority": market.authority.to_string(),
+        "title": market.metadata.title,
+        "symbol": market.metadata.symbol,
+        "decimals": market.metadata.decimals,
+        "image_uri": market.metadata.image_uri,
+        "kind": market_kind(market.kind),
+        "status": market_status(market, now),
+        "started": market.started,
+        "start": market.start,
+        "end": market.end,
+        "payout_multiplier": market.payout_multiplier,
+        "high_exposure": market.high_exposure,
+        "low_exposure": market.low_exposure,
+        "liability": market.liability,
+        "open_predictions": market.open_predictions,
+        "oracle": market.oracle.to_string(),
+        "resolved": market.resolved,
+        "finalized": market.finalized,
+        "final_price": market.resolved.then(|| price_json(market.final_price)),
+        "resolved_at": market.resolved_at,
+    })
+}
+
+pub fn prediction_json(prediction_pda: &Pubkey, prediction: &Prediction) -> Value {
+    json!({
+        "address": prediction_pda.to_string(),
+        "market": prediction.market.to_string(),
+        "user": prediction.user.to_string(),
+        "prediction": prediction.prediction,
+        "bucket": prediction.bucket,
+        "market_price": price_json(prediction.market_price),
+        "amount": prediction.amount,
+        "payout_multiplier": prediction.payout_multiplier,
+        "payout": prediction.payout(),
+        "status": prediction_status(prediction.status),
+        "vault": prediction.vault.to_string(),
+    })
+}
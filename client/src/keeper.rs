@@ -0,0 +1,323 @@
+use std::{thread::sleep, time::Duration};
+
+use anchor_client::{
+    solana_client::rpc_filter::{Memcmp, RpcFilterType},
+    solana_sdk::{instruction::Instruction, signature::Keypair, signer::Signer},
+    Program, RequestBuilder,
+};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anyhow::Context;
+use chrono::Utc;
+use serde_json::json;
+use solana_program::pubkey::Pubkey;
+use zone::{Market, Prediction, PredictionStatus};
+
+use crate::{
+    config::Config,
+    output::{OutputFormat, ProgramErrorInfo},
+    price_source::{self, PriceSourceKind},
+    transaction::{Outcome, SendOrSimulate},
+};
+
+/// Moves markets along as they come due: starts this wallet's markets, samples TWAP
+/// prices, resolves and finalizes ended markets, and settles their predictions. Prices
+/// from `--price-source` are published to the oracles this wallet publishes on the way.
+pub struct Keeper<'a> {
+    pub program: &'a Program<&'a Keypair>,
+    pub config: &'a Config,
+    /// Seconds the markets started by the keeper run for, none to leave them alone
+    pub duration: Option<i64>,
+    /// Only market kept, all of them if none
+    pub market: Option<Pubkey>,
+    pub batch_size: usize,
+    pub retries: u32,
+}
+
+impl Keeper<'_> {
+    pub fn run(&self, interval: Duration, once: bool) -> anyhow::Result<()> {
+        loop {
+            match self.poll() {
+                Err(err) if once => return Err(err),
+                // Like a market failing, a failed poll is retried on the next one
+                Err(err) => self.log("poll markets", &self.program.id(), &Err(err)),
+                Ok(()) => {}
+            }
+
+            if once {
+                return Ok(());
+            }
+            sleep(interval);
+        }
+    }
+
+    /// Moves each market one step along.
+    pub fn poll(&self) -> anyhow::Result<()> {
+        let now = Utc::now().timestamp();
+        // Markets not migrated yet fail to deserialize and are left out
+        let markets = self
+            .program
+            .accounts_lazy::<Market>(vec![])
+            .context("Failed to fetch market accounts")?
+            .filter_map(Result::ok)
+            .filter(|(market_pda, _)| self.market.is_none_or(|market| market == *market_pda));
+
+        // A market failing doesn't hold up the others, it is retried on the next poll
+        for (market_pda, market) in markets {
+            self.keep(&market_pda, &market, now);
+        }
+
+        Ok(())
+    }
+
+    fn keep(&self, market_pda: &Pubkey, market: &Market, now: i64) {
+        if !market.started {
+            if let Some(duration) = self.duration {
+                if market.authority == self.payer() && market.oracle != Pubkey::default() {
+                    self.start_market(market_pda, market, now + duration);
+                }
+            }
+        } else if market.end > now {
+            if market.twap_window > 0
+                && now >= market.end - market.twap_window
+                && twap_sample_due(market, now)
+            {
+                self.sample_price(market_pda, market);
+            }
+        } else if !market.resolved {
+            self.resolve_market(market_pda, market);
+        } else if !market.finalized {
+            if now >= market.resolved_at + market.dispute_period {
+                self.finalize_resolution(market_pda);
+            }
+        } else if market.open_predictions > 0 {
+            self.settle_predictions(market_pda, market);
+        }
+    }
+
+    fn start_market(&self, market_pda: &Pubkey, market: &Market, end: i64) {
+        let Some(publish) = self.publish_price(market_pda, market) else {
+            return;
+        };
+
+        self.attempt("start market", market_pda, || {
+            self.request(&publish)
+                .accounts(zone::accounts::StartMarket {
+                    market: *market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                })
+                .args(zone::instruction::StartMarket { end })
+                .send_or_simulate(self.config)
+        });
+    }
+
+    fn sample_price(&self, market_pda: &Pubkey, market: &Market) {
+        let Some(publish) = self.publish_price(market_pda, market) else {
+            return;
+        };
+
+        self.attempt("sample price", market_pda, || {
+            self.request(&publish)
+                .accounts(zone::accounts::SamplePrice {
+                    market: *market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                })
+                .args(zone::instruction::SamplePrice {})
+                .send_or_simulate(self.config)
+        });
+    }
+
+    fn resolve_market(&self, market_pda: &Pubkey, market: &Market) {
+        // TWAP markets settle on their samples if any, the oracle price doesn't matter then
+        let publish = if market.twap_window > 0 && market.twap_sample_count > 0 {
+            Some(vec![])
+        } else {
+            self.publish_price(market_pda, market)
+        };
+        let Some(publish) = publish else {
+            return;
+        };
+
+        self.attempt("resolve market", market_pda, || {
+            self.request(&publish)
+                .accounts(zone::accounts::ResolveMarket {
+                    market: *market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                })
+                .args(zone::instruction::ResolveMarket {})
+                .send_or_simulate(self.config)
+        });
+    }
+
+    fn finalize_resolution(&self, market_pda: &Pubkey) {
+        self.attempt("finalize resolution", market_pda, || {
+            self.program
+                .request()
+                .accounts(zone::accounts::FinalizeResolution {
+                    market: *market_pda,
+                })
+                .args(zone::instruction::FinalizeResolution {})
+                .send_or_simulate(self.config)
+        });
+    }
+
+    /// Settles the open predictions of the market, `batch_size` per transaction. Winning
+    /// ones are paid out and losing ones release the liability they hold in their vault.
+    fn settle_predictions(&self, market_pda: &Pubkey, market: &Market) {
+        let filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            crate::PREDICTION_MARKET_OFFSET,
+            market_pda.as_ref(),
+        ))];
+        let predictions = match self.program.accounts_lazy::<Prediction>(filters) {
+            Ok(predictions) => predictions.filter_map(Result::ok),
+            Err(err) => {
+                let err = anyhow::Error::new(err).context("Failed to fetch prediction accounts");
+                self.log("settle predictions", market_pda, &Err(err));
+                return;
+            }
+        };
+
+        let instructions: Vec<Instruction> = predictions
+            .filter(|(_, prediction)| prediction.status == PredictionStatus::Open)
+            .map(|(prediction_pda, prediction)| {
+                let vault_exposure = crate::get_vault_exposure_pda(
+                    &prediction.vault,
+                    market_pda,
+                    market.start,
+                    &self.program.id(),
+                );
+
+                if market.is_winning(&prediction, market.final_price) {
+                    Instruction {
+                        program_id: self.program.id(),
+                        accounts: zone::accounts::SettleWinningPrediction {
+                            vault: prediction.vault,
+                            prediction: prediction_pda,
+                            vault_exposure,
+                            user: prediction.user,
+                            market: *market_pda,
+                        }
+                        .to_account_metas(None),
+                        data: zone::instruction::SettleWinningPrediction {}.data(),
+                    }
+                } else {
+                    Instruction {
+                        program_id: self.program.id(),
+                        accounts: zone::accounts::SettleLosingPrediction {
+                            vault: prediction.vault,
+                            prediction: prediction_pda,
+                            vault_exposure,
+                            market: *market_pda,
+                        }
+                        .to_account_metas(None),
+                        data: zone::instruction::SettleLosingPrediction {}.data(),
+                    }
+                }
+            })
+            .collect();
+
+        for batch in instructions.chunks(self.batch_size.max(1)) {
+            let action = format!("settle {} predictions", batch.len());
+
+            self.attempt(&action, market_pda, || {
+                self.request(batch).send_or_simulate(self.config)
+            });
+        }
+    }
+
+    /// Instructions publishing the price of the market token to the market oracle, when
+    /// it comes from another source and this wallet publishes the oracle. None if the price
+    /// couldn't be fetched, which is logged.
+    fn publish_price(&self, market_pda: &Pubkey, market: &Market) -> Option<Vec<Instruction>> {
+        if let PriceSourceKind::Oracle = self.config.price_source {
+            return Some(vec![]);
+        }
+
+        match price_source::current_price(self.program, &self.config.price_source, market) {
+            Ok((_price, publish)) => Some(publish.into_iter().collect()),
+            Err(err) => {
+                self.log("fetch price", market_pda, &Err(err));
+                None
+            }
+        }
+    }
+
+    /// Request running `instructions` before the one of its `args`.
+    fn request(&self, instructions: &[Instruction]) -> RequestBuilder<'_, &Keypair> {
+        instructions
+            .iter()
+            .fold(self.program.request(), |request, instruction| {
+                request.instruction(instruction.clone())
+            })
+    }
+
+    /// Sends a transaction, retrying with exponential backoff unless the program rejects
+    /// it, and logs the outcome.
+    fn attempt(&self, action: &str, account: &Pubkey, send: impl Fn() -> anyhow::Result<Outcome>) {
+        let mut result = send();
+
+        for attempt in 1..self.retries {
+            match &result {
+                Err(err) if ProgramErrorInfo::from_error(err).is_none() => {
+                    sleep(Duration::from_secs(1u64 << attempt.min(6)));
+                    result = send();
+                }
+                _ => break,
+            }
+        }
+
+        self.log(action, account, &result);
+    }
+
+    fn log(&self, action: &str, account: &Pubkey, result: &anyhow::Result<Outcome>) {
+        let time = Utc::now();
+
+        match self.config.output {
+            OutputFormat::Text => {
+                let time = time.format("%Y-%m-%d %H:%M:%S");
+                match result {
+                    Ok(Outcome::Sent(sig)) => {
+                        println!(
+                            "[{time}] {account} {action}: {}",
+                            self.config.explorer_url(sig)
+                        )
+                    }
+                    Ok(Outcome::Simulated(result)) => println!(
+                        "[{time}] {account} {action}: simulated, {} compute units",
+                        result.units_consumed.unwrap_or_default()
+                    ),
+                    Err(err) => eprintln!("[{time}] {account} {action} failed: {err:#}"),
+                }
+            }
+            OutputFormat::Json => {
+                let mut entry = match result {
+                    Ok(outcome) => self.config.sent_json(outcome, &[]),
+                    Err(err) => json!({
+                        "error": format!("{err:#}"),
+                        "program_error": ProgramErrorInfo::from_error(err)
+                            .map(|program_error| program_error.to_json()),
+                    }),
+                };
+                entry["time"] = json!(time.timestamp());
+                entry["action"] = json!(action);
+                entry["account"] = json!(account.to_string());
+                println!("{entry}");
+            }
+        }
+    }
+
+    fn payer(&self) -> Pubkey {
+        self.config.payer.pubkey()
+    }
+}
+
+/// Whether enough time passed since the last TWAP sample for the program to take another.
+fn twap_sample_due(market: &Market, now: i64) -> bool {
+    let min_interval = (market.twap_window / zone::constants::TWAP_SAMPLES as i64).max(1);
+
+    market
+        .last_twap_sample()
+        .is_none_or(|last| now - last.timestamp >= min_interval)
+}
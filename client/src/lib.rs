@@ -0,0 +1,145 @@
+use solana_program::pubkey::Pubkey;
+
+pub mod config;
+pub mod display;
+pub mod keeper;
+pub mod output;
+pub mod price_source;
+pub mod transaction;
+
+// Prediction.user follows the discriminator and the version byte, Prediction.market follows it
+pub const PREDICTION_USER_OFFSET: usize = 8 + 1;
+pub const PREDICTION_MARKET_OFFSET: usize = PREDICTION_USER_OFFSET + 32;
+
+/// Predictions are per market run, a market reopened after being closed starts new ones.
+pub fn get_prediction_pda(
+    market_pda: &Pubkey,
+    market_start: i64,
+    user: &Pubkey,
+    program_id: &Pubkey,
+) -> Pubkey {
+    let (prediction_pda, _bump) = Pubkey::find_program_address(
+        &[
+            b"prediction",
+            market_pda.as_ref(),
+            user.as_ref(),
+            &market_start.to_le_bytes(),
+        ],
+        program_id,
+    );
+
+    prediction_pda
+}
+
+pub fn get_vault_exposure_pda(
+    vault_pda: &Pubkey,
+    market_pda: &Pubkey,
+    market_start: i64,
+    program_id: &Pubkey,
+) -> Pubkey {
+    let (vault_exposure_pda, _bump) = Pubkey::find_program_address(
+        &[
+            b"vault_exposure",
+            vault_pda.as_ref(),
+            market_pda.as_ref(),
+            &market_start.to_le_bytes(),
+        ],
+        program_id,
+    );
+
+    vault_exposure_pda
+}
+
+/// Splits a decimal like `1.50` into its digits and number of decimals, at most `max_decimals`.
+fn parse_decimal(value: &str, what: &str, max_decimals: usize) -> Result<(String, usize), String> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    let digits = format!("{int}{frac}");
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid {what}: {value}"));
+    }
+    if frac.len() > max_decimals {
+        return Err(format!(
+            "More than {max_decimals} decimals in {what}: {value}"
+        ));
+    }
+
+    Ok((digits, frac.len()))
+}
+
+/// Parses a decimal price like `0.00002` into a mantissa and exponent.
+pub fn parse_price(price: &str) -> Result<zone::Price, String> {
+    let (digits, decimals) = parse_decimal(price, "price", zone::constants::MAX_EXPO as usize)?;
+
+    let mantissa = digits
+        .parse::<u64>()
+        .map_err(|_| format!("Too many digits in price: {price}"))?;
+
+    Ok(zone::Price {
+        mantissa,
+        expo: -(decimals as i32),
+    })
+}
+
+/// Parses a decimal amount of SOL like `0.5` into lamports. LP shares have as many decimals.
+pub fn parse_sol(amount: &str) -> Result<u64, String> {
+    let (digits, decimals) = parse_decimal(amount, "amount", 9)?;
+
+    format!("{digits}{}", "0".repeat(9 - decimals))
+        .parse::<u64>()
+        .map_err(|_| format!("Amount too large: {amount}"))
+}
+
+/// Like `parse_sol`, for amounts that can't be zero.
+pub fn parse_nonzero_sol(amount: &str) -> Result<u64, String> {
+    match parse_sol(amount)? {
+        0 => Err(format!("Amount must be more than zero: {amount}")),
+        lamports => Ok(lamports),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_price() {
+        let price = parse_price("0.00002").unwrap();
+        assert_eq!((price.mantissa, price.expo), (2, -5));
+
+        let price = parse_price("1.50").unwrap();
+        assert_eq!((price.mantissa, price.expo), (150, -2));
+
+        let price = parse_price("12").unwrap();
+        assert_eq!((price.mantissa, price.expo), (12, 0));
+
+        assert!(parse_price("").is_err());
+        assert!(parse_price("1.2.3").is_err());
+        assert!(parse_price("-1").is_err());
+        assert!(parse_price("0.0000000000000000001").is_err());
+        assert!(parse_price("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn test_parse_sol() {
+        assert_eq!(parse_sol("1").unwrap(), 1_000_000_000);
+        assert_eq!(parse_sol("0.5").unwrap(), 500_000_000);
+        assert_eq!(parse_sol("0.000000001").unwrap(), 1);
+        assert_eq!(parse_sol("0").unwrap(), 0);
+
+        assert!(parse_sol("").is_err());
+        assert!(parse_sol("1.2.3").is_err());
+        assert!(parse_sol("-1").is_err());
+        assert!(parse_sol("0.0000000001").is_err());
+        assert!(parse_sol("99999999999").is_err());
+    }
+
+    #[test]
+    fn test_parse_nonzero_sol() {
+        assert_eq!(parse_nonzero_sol("0.5").unwrap(), 500_000_000);
+
+        assert!(parse_nonzero_sol("0").is_err());
+        assert!(parse_nonzero_sol("0.000").is_err());
+        assert!(parse_nonzero_sol("abc").is_err());
+    }
+}
@@ -1,19 +1,36 @@
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use anchor_client::{
-    solana_sdk::{
-        commitment_config::CommitmentConfig, signature::read_keypair_file, signer::Signer,
-    },
-    Client, Cluster,
+    solana_client::rpc_filter::{Memcmp, RpcFilterType},
+    solana_sdk::{signature::Keypair, signer::Signer},
+    Client, ClientError, Program, RequestBuilder,
 };
 use anchor_lang::system_program;
-use chrono::DateTime;
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
 use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
 
+use serde_json::json;
+
+use client::{
+    config::{Config, ConfigArgs},
+    display, get_prediction_pda, get_vault_exposure_pda,
+    keeper::Keeper,
+    output::{self, OutputFormat},
+    parse_nonzero_sol, parse_price, parse_sol,
+    price_source::current_price,
+    transaction::SendOrSimulate,
+    PREDICTION_USER_OFFSET,
+};
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -23,7 +40,120 @@ enum Commands {
     /// Initialize the vault
     Initialize {
         /// SOL
+        #[arg(value_parser = parse_sol)]
         vault_amount: u64,
+
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Issue LP shares of the vault, backing the funds already in it
+    InitializeLpMint {
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Set how long LPs wait to withdraw while predictions are open
+    SetWithdrawalDelay {
+        /// Seconds
+        withdrawal_delay: i64,
+
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Set limits on the payout the vault can owe
+    SetRiskLimits {
+        /// SOL owed by the vault in a single market, 0 for none
+        #[arg(value_parser = parse_sol)]
+        max_market_liability: u64,
+
+        /// SOL owed by the vault, 0 for none
+        #[arg(value_parser = parse_sol)]
+        max_total_liability: u64,
+
+        /// SOL of difference between the HIGH and LOW bets the vault backs in a market, 0 for none
+        #[arg(value_parser = parse_sol)]
+        max_imbalance: u64,
+
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Show how much of the risk limits of the vault is used
+    Utilization {
+        /// Markets to report on
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_addresses: Vec<String>,
+
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Show the vault account
+    ShowVault {
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Show the market account of a token
+    ShowMarket {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Show a prediction on the market of a token
+    ShowPrediction {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Owner of the prediction, this wallet by default
+        #[arg(long)]
+        user: Option<String>,
+    },
+
+    /// List all markets of the program
+    ListMarkets,
+
+    /// List the predictions of this wallet
+    MyPositions,
+
+    /// Provide liquidity to the vault in exchange for LP shares
+    Deposit {
+        /// SOL
+        #[arg(value_parser = parse_nonzero_sol)]
+        amount: u64,
+
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Request to withdraw liquidity from the vault
+    RequestWithdrawal {
+        /// LP shares
+        #[arg(value_parser = parse_nonzero_sol)]
+        shares: u64,
+
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Burn the requested LP shares for their value in SOL
+    Withdraw {
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
     },
 
     /// Initialize the market
@@ -34,6 +164,44 @@ enum Commands {
 
         /// Multiplier for payout (e.g., 200 for 2x)
         payout_multiplier: u64,
+
+        /// Title shown for the market
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Symbol of the token
+        #[arg(long, default_value = "", requires = "title")]
+        symbol: String,
+
+        /// Decimals to display prices of the token with
+        #[arg(long, default_value_t = 0, requires = "title")]
+        decimals: u8,
+
+        /// Image of the token
+        #[arg(long, default_value = "", requires = "title")]
+        image_uri: String,
+    },
+
+    /// Update the title, symbol, decimals and image of the market before it starts
+    UpdateMarketMetadata {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Title shown for the market
+        title: String,
+
+        /// Symbol of the token
+        #[arg(long, default_value = "")]
+        symbol: String,
+
+        /// Decimals to display prices of the token with
+        #[arg(long, default_value_t = 0)]
+        decimals: u8,
+
+        /// Image of the token
+        #[arg(long, default_value = "")]
+        image_uri: String,
     },
 
     /// Start the market
@@ -52,35 +220,273 @@ enum Commands {
         /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
         token_address: String,
 
-        /// HIGH => >= 1
-        /// LOW => 0
+        /// HIGH (or MOVED) => >= 1
+        /// LOW (or STAYED) => 0
         prediction: u8,
 
         /// SOL
+        #[arg(value_parser = parse_nonzero_sol)]
         amount: u64,
 
-        /// Market price at prediction time
-        current_price: u64,
+        /// Bucket index for range markets
+        #[arg(long)]
+        bucket: Option<u8>,
+
+        /// Lowest payout multiplier accepted (e.g. 180 for 1.8x)
+        #[arg(long, default_value_t = 0)]
+        min_multiplier: u64,
+
+        /// Vault number
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Configure price buckets for a range market
+    SetBuckets {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Ascending bounds between buckets in basis points (e.g. -1000,0,1000)
+        #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+        bounds: Vec<i64>,
+
+        /// Multiplier for payout of each bucket (e.g. 400,200,200,400)
+        #[arg(long, value_delimiter = ',')]
+        multipliers: Vec<u64>,
+    },
+
+    /// Turn the market into a bet on moving at least a percentage in either direction
+    SetMoveThreshold {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Basis points (e.g. 500 for 5%)
+        move_threshold: u64,
+    },
+
+    /// Skew the payout multiplier against the side with more exposure
+    SetDynamicOdds {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// SOL of virtual liquidity damping the odds curve, 0 for fixed odds
+        #[arg(value_parser = parse_sol)]
+        odds_liquidity: u64,
+
+        /// Upper bound of the offered multiplier (e.g. 500 for 5x)
+        max_multiplier: u64,
+    },
+
+    /// Initialize a price feed published by this wallet
+    InitializePriceFeed {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Publish a price to the price feed
+    UpdatePriceFeed {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Current price of token (e.g., 0.00002)
+        #[arg(value_parser = parse_price)]
+        price: zone::Price,
+
+        /// Confidence interval around the price (e.g., 0.0000001)
+        #[arg(long, value_parser = parse_price, default_value = "0")]
+        conf: zone::Price,
+    },
+
+    /// Use the price feed published by this wallet as the market oracle
+    SetOracle {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Oldest oracle price accepted, in seconds
+        #[arg(long, default_value_t = 60)]
+        max_staleness: i64,
+
+        /// Widest confidence interval accepted, in basis points of the price
+        #[arg(long, default_value_t = 100)]
+        max_confidence: u64,
+
+        /// Price feed used when the oracle is stale or uncertain
+        #[arg(long)]
+        fallback_oracle: Option<String>,
+    },
+
+    /// Set the haircut on the fair value of cashed out positions
+    SetCashOutSpread {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Basis points (e.g. 200 for 2%)
+        cash_out_spread: u64,
+    },
+
+    /// Settle the market on a TWAP over the last seconds before end
+    SetTwapWindow {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Seconds (e.g. 600 for the last 10 minutes), 0 for spot
+        twap_window: i64,
+    },
+
+    /// Record the oracle price for the TWAP of the market
+    SamplePrice {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Record the final price of the finished market
+    ResolveMarket {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Let the final price be corrected for a while after resolution
+    SetDisputePeriod {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Seconds (e.g. 3600 for an hour), 0 to settle right after resolution
+        dispute_period: i64,
+
+        /// Allowed to correct the final price besides this wallet
+        resolver: String,
+    },
+
+    /// Correct the final price during the dispute period
+    DisputeResolution {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Corrected final price of token (e.g., 0.00002)
+        #[arg(value_parser = parse_price)]
+        corrected_price: zone::Price,
+    },
+
+    /// Make the final price settleable once the dispute period is over
+    FinalizeResolution {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Settle your prediction once the market is resolved
+    Settle {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Close the open prediction before the market ends
+    CashOut {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Close the market once all its predictions are closed, reclaiming its rent
+    CloseMarket {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Upgrade a market created before accounts were versioned
+    MigrateMarket {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+    },
+
+    /// Upgrade a prediction created before accounts were versioned
+    MigratePrediction {
+        /// BONK: DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263
+        /// MOTHER: 3S8qX1MsMqRbiwKg2cQyx7nis1oHMgaCuc9c4VfvVdPN
+        token_address: String,
+
+        /// Owner of the prediction, this wallet by default
+        #[arg(long)]
+        user: Option<String>,
+
+        /// Exponent of the market price recorded by the prediction (e.g. -5)
+        #[arg(long, allow_hyphen_values = true)]
+        expo: i32,
+
+        /// Vault backing the prediction from now on
+        #[arg(long, default_value_t = 0)]
+        vault: u8,
+    },
+
+    /// Start, resolve and finalize markets as they come due, and settle their predictions
+    Keeper {
+        /// Seconds between polls of the markets
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+
+        /// Start the unstarted markets of this wallet, to run for this many seconds
+        #[arg(long)]
+        duration: Option<i64>,
+
+        /// Only keep the market of this token, all markets by default
+        #[arg(long)]
+        token_address: Option<String>,
+
+        /// Predictions settled per transaction
+        #[arg(long, default_value_t = 5)]
+        batch_size: usize,
+
+        /// Attempts at each transaction before waiting for the next poll
+        #[arg(long, default_value_t = 3)]
+        retries: u32,
+
+        /// Poll once and exit
+        #[arg(long)]
+        once: bool,
     },
 }
 
 fn main() {
-    // devnet
-    let program_id = "2BqXsVFG5Woo6VVg6pK4RM7g6W7YZwCSM9wYou8kzu6F";
+    dotenv::dotenv().ok();
 
-    let anchor_wallet = std::env::var("ANCHOR_WALLET").unwrap();
-    let payer = read_keypair_file(&anchor_wallet).unwrap();
+    let cli = Cli::parse();
+    if let Err(err) = run(&cli) {
+        output::report_error(cli.config.output, &err);
+        std::process::exit(1);
+    }
+}
 
-    let client = Client::new_with_options(Cluster::Devnet, &payer, CommitmentConfig::confirmed());
-    let program_id = Pubkey::from_str(program_id).unwrap();
-    let program = client.program(program_id).unwrap();
+fn run(cli: &Cli) -> anyhow::Result<()> {
+    let config = Config::load(&cli.config)?;
+    let payer = &config.payer;
+    let program_id = config.program_id;
 
-    let (vault_pda, _bump) = Pubkey::find_program_address(&[b"vault"], &program_id);
+    let client = Client::new_with_options(config.cluster.clone(), payer, config.commitment);
+    let program = client.program(program_id)?;
 
-    let cli = Cli::parse();
     match &cli.command {
-        Commands::Initialize { vault_amount } => {
-            let sig = program
+        Commands::Initialize {
+            vault_amount,
+            vault,
+        } => {
+            let vault_pda = get_vault_pda(*vault, &program_id);
+
+            let outcome = program
                 .request()
                 .accounts(zone::accounts::Initialize {
                     vault: vault_pda,
@@ -88,95 +494,1216 @@ fn main() {
                     system_program: system_program::ID,
                 })
                 .args(zone::instruction::Initialize {
-                    amount: vault_amount * LAMPORTS_PER_SOL,
-                    vault_num: 0,
+                    amount: *vault_amount,
+                    vault_num: *vault,
                 })
-                .send()
-                .expect("Failed to send initialize transaction");
+                .send_or_simulate(&config)
+                .context("Failed to send initialize transaction")?;
 
-            println!("Successfully initialized: https://solscan.io/tx/{sig}?cluster=devnet");
+            config.sent(
+                "Successfully initialized",
+                &outcome,
+                &[("vault", vault_pda)],
+            );
         }
-        Commands::InitializeMarket {
-            token_address,
-            payout_multiplier,
+        Commands::InitializeLpMint { vault } => {
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
+            let (lp_mint_pda, _bump) =
+                Pubkey::find_program_address(&[b"lp_mint", vault_pda.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::InitializeLpMint {
+                    vault: vault_pda,
+                    lp_mint: lp_mint_pda,
+                    authority_lp_account: get_associated_token_address(
+                        &payer.pubkey(),
+                        &lp_mint_pda,
+                    ),
+                    authority: payer.pubkey(),
+                    token_program: anchor_spl::token::ID,
+                    associated_token_program: anchor_spl::associated_token::ID,
+                    system_program: system_program::ID,
+                })
+                .args(zone::instruction::InitializeLpMint { vault_num: *vault })
+                .send_or_simulate(&config)
+                .context("Failed to send initialize LP mint transaction")?;
+
+            config.sent(
+                "Successfully initialized LP mint",
+                &outcome,
+                &[("vault", vault_pda), ("lp_mint", lp_mint_pda)],
+            );
+        }
+        Commands::SetWithdrawalDelay {
+            withdrawal_delay,
+            vault,
         } => {
-            let token_account = Pubkey::from_str(token_address).unwrap();
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
 
-            let (market_pda, _bump) =
-                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetWithdrawalDelay {
+                    vault: vault_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetWithdrawalDelay {
+                    vault_num: *vault,
+                    withdrawal_delay: *withdrawal_delay,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set withdrawal delay transaction")?;
+
+            config.sent(
+                "Successfully set withdrawal delay",
+                &outcome,
+                &[("vault", vault_pda)],
+            );
+        }
+        Commands::SetRiskLimits {
+            max_market_liability,
+            max_total_liability,
+            max_imbalance,
+            vault,
+        } => {
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
 
-            let sig = program
+            let outcome = program
                 .request()
-                .accounts(zone::accounts::InitializeMarket {
-                    market: market_pda,
+                .accounts(zone::accounts::SetRiskLimits {
+                    vault: vault_pda,
                     authority: payer.pubkey(),
-                    system_program: system_program::ID,
                 })
-                .args(zone::instruction::InitializeMarket {
-                    token_account,
-                    payout_multiplier: *payout_multiplier,
+                .args(zone::instruction::SetRiskLimits {
+                    vault_num: *vault,
+                    max_market_liability: *max_market_liability,
+                    max_total_liability: *max_total_liability,
+                    max_imbalance: *max_imbalance,
                 })
-                .send()
-                .expect("Failed to send initialize market transaction");
+                .send_or_simulate(&config)
+                .context("Failed to send set risk limits transaction")?;
 
-            println!("Successfully initialized market: https://solscan.io/tx/{sig}?cluster=devnet");
+            config.sent(
+                "Successfully set risk limits",
+                &outcome,
+                &[("vault", vault_pda)],
+            );
         }
-        Commands::StartMarket { token_address, end } => {
-            let token_account = Pubkey::from_str(token_address).unwrap();
+        Commands::Utilization {
+            token_addresses,
+            vault,
+        } => {
+            let (vault_pda, vault) = fetch_vault(&program, *vault)?;
 
+            let mut markets = vec![];
+            for token_address in token_addresses {
+                let token_account = parse_pubkey(token_address)?;
+                let (market_pda, _bump) =
+                    Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+                let market = program
+                    .account::<zone::Market>(market_pda)
+                    .context("Failed to fetch market account")?;
+                // Vaults that never backed a prediction in the market have no exposure to it
+                let (liability, imbalance) = program
+                    .account::<zone::VaultExposure>(get_vault_exposure_pda(
+                        &vault_pda,
+                        &market_pda,
+                        market.start,
+                        &program_id,
+                    ))
+                    .map_or((0, 0), |vault_exposure| {
+                        (vault_exposure.liability, vault_exposure.imbalance())
+                    });
+
+                markets.push((token_address, market_pda, liability, imbalance));
+            }
+
+            match config.output {
+                OutputFormat::Text => {
+                    println!(
+                        "Vault {vault_pda}: {} open predictions",
+                        vault.open_predictions
+                    );
+                    println!(
+                        "  liability: {}",
+                        utilization(vault.liabilities, vault.max_total_liability)
+                    );
+                    println!(
+                        "  deposits: {}, realized P&L: {}",
+                        display::sol(vault.deposits),
+                        display::signed_sol(vault.realized_pnl)
+                    );
+
+                    for (token_address, _market_pda, liability, imbalance) in &markets {
+                        println!("Market {token_address}:");
+                        println!(
+                            "  liability: {}",
+                            utilization(*liability, vault.max_market_liability)
+                        );
+                        println!(
+                            "  imbalance: {}",
+                            utilization(*imbalance, vault.max_imbalance)
+                        );
+                    }
+                }
+                OutputFormat::Json => {
+                    let markets: Vec<_> = markets
+                        .iter()
+                        .map(|(token_address, market_pda, liability, imbalance)| {
+                            json!({
+                                "token_account": token_address,
+                                "market": market_pda.to_string(),
+                                "liability": liability,
+                                "imbalance": imbalance,
+                            })
+                        })
+                        .collect();
+                    let mut report = display::vault_json(&vault_pda, &vault);
+                    report["markets"] = json!(markets);
+                    println!("{report}");
+                }
+            }
+        }
+        Commands::ShowVault { vault } => {
+            let (vault_pda, vault) = fetch_vault(&program, *vault)?;
+
+            config.show(
+                || display::print_vault(&vault_pda, &vault),
+                || display::vault_json(&vault_pda, &vault),
+            );
+        }
+        Commands::ShowMarket { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
             let (market_pda, _bump) =
                 Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
 
-            let end = format!("{end} +0000");
-            let end = DateTime::parse_from_str(&end, "%Y-%m-%d %H:%M:%S %z").unwrap();
-            // let end = Utc::now() + chrono::Duration::days(1);
-
-            let sig = program
-                .request()
-                .accounts(zone::accounts::StartMarket { market: market_pda })
-                .args(zone::instruction::StartMarket {
-                    end: end.timestamp(),
-                })
-                .send()
-                .expect("Failed to send start market transaction");
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
 
-            println!("Successfully start market: https://solscan.io/tx/{sig}?cluster=devnet");
+            let now = Utc::now().timestamp();
+            config.show(
+                || display::print_market(&market_pda, &market, now),
+                || display::market_json(&market_pda, &market, now),
+            );
         }
-        Commands::Bet {
+        Commands::ShowPrediction {
             token_address,
-            prediction,
-            amount,
-            current_price,
+            user,
         } => {
-            let token_account = Pubkey::from_str(token_address).unwrap();
+            let token_account = parse_pubkey(token_address)?;
+            let user = match user {
+                Some(user) => parse_pubkey(user)?,
+                None => payer.pubkey(),
+            };
             let (market_pda, _bump) =
                 Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
 
-            let (prediction_pda, _bump) = Pubkey::find_program_address(
-                &[b"prediction", market_pda.as_ref(), payer.pubkey().as_ref()],
-                &program_id,
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+            let prediction_pda = get_prediction_pda(&market_pda, market.start, &user, &program_id);
+            let prediction: zone::Prediction = program
+                .account(prediction_pda)
+                .context("Failed to fetch prediction account")?;
+
+            config.show(
+                || display::print_prediction(&prediction_pda, &prediction, Some(&market)),
+                || display::prediction_json(&prediction_pda, &prediction),
             );
+        }
+        Commands::ListMarkets => {
+            let now = Utc::now().timestamp();
+            // Markets not migrated yet fail to deserialize and are left out
+            let markets = program
+                .accounts_lazy::<zone::Market>(vec![])
+                .context("Failed to fetch market accounts")?
+                .filter_map(Result::ok);
 
-            let prediction = *prediction != 0;
+            match config.output {
+                OutputFormat::Text => {
+                    for (market_pda, market) in markets {
+                        println!("{market_pda} {}", display::market_title(&market));
+                        println!(
+                            "  {}, {}, liability {}",
+                            display::market_kind(market.kind),
+                            display::market_status(&market, now),
+                            display::sol(market.liability)
+                        );
+                        if market.started {
+                            println!("  ends {}", display::time(market.end));
+                        }
+                    }
+                }
+                OutputFormat::Json => {
+                    let markets: Vec<_> = markets
+                        .map(|(market_pda, market)| display::market_json(&market_pda, &market, now))
+                        .collect();
+                    println!("{}", json!({ "markets": markets }));
+                }
+            }
+        }
+        Commands::MyPositions => {
+            let filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                PREDICTION_USER_OFFSET,
+                payer.pubkey().as_ref(),
+            ))];
+            let predictions = program
+                .accounts_lazy::<zone::Prediction>(filters)
+                .context("Failed to fetch prediction accounts")?
+                .filter_map(Result::ok);
+
+            match config.output {
+                OutputFormat::Text => {
+                    for (prediction_pda, prediction) in predictions {
+                        // Settled predictions outlive their market once it is closed
+                        let market = match program.account::<zone::Market>(prediction.market) {
+                            Ok(market) => Some(market),
+                            Err(ClientError::AccountNotFound) => None,
+                            Err(err) => {
+                                return Err(err).context("Failed to fetch market account");
+                            }
+                        };
+
+                        display::print_prediction(&prediction_pda, &prediction, market.as_ref());
+                    }
+                }
+                OutputFormat::Json => {
+                    let predictions: Vec<_> = predictions
+                        .map(|(prediction_pda, prediction)| {
+                            display::prediction_json(&prediction_pda, &prediction)
+                        })
+                        .collect();
+                    println!("{}", json!({ "positions": predictions }));
+                }
+            }
+        }
+        Commands::Deposit { amount, vault } => {
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
+            let (lp_mint_pda, _bump) =
+                Pubkey::find_program_address(&[b"lp_mint", vault_pda.as_ref()], &program_id);
 
-            let sig = program
+            let outcome = program
                 .request()
-                .accounts(zone::accounts::CreatePrediction {
-                    prediction: prediction_pda,
-                    user: payer.pubkey(),
-                    market: market_pda,
-                    system_program: system_program::ID,
+                .accounts(zone::accounts::Deposit {
                     vault: vault_pda,
+                    lp_mint: lp_mint_pda,
+                    depositor_lp_account: get_associated_token_address(
+                        &payer.pubkey(),
+                        &lp_mint_pda,
+                    ),
+                    depositor: payer.pubkey(),
+                    token_program: anchor_spl::token::ID,
+                    associated_token_program: anchor_spl::associated_token::ID,
+                    system_program: system_program::ID,
                 })
-                .args(zone::instruction::CreatePrediction {
-                    prediction,
-                    amount: amount * LAMPORTS_PER_SOL,
-                    current_price: *current_price,
+                .args(zone::instruction::Deposit {
+                    vault_num: *vault,
+                    amount: *amount,
                 })
-                .send()
-                .expect("Failed to send create prediction transaction");
+                .send_or_simulate(&config)
+                .context("Failed to send deposit transaction")?;
 
-            println!("Successfully create prediction: https://solscan.io/tx/{sig}?cluster=devnet");
+            config.sent(
+                "Successfully deposited",
+                &outcome,
+                &[("vault", vault_pda), ("lp_mint", lp_mint_pda)],
+            );
         }
+        Commands::RequestWithdrawal { shares, vault } => {
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
+            let (lp_mint_pda, _bump) =
+                Pubkey::find_program_address(&[b"lp_mint", vault_pda.as_ref()], &program_id);
+            let (lp_position_pda, _bump) = Pubkey::find_program_address(
+                &[b"lp_position", vault_pda.as_ref(), payer.pubkey().as_ref()],
+                &program_id,
+            );
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::RequestWithdrawal {
+                    vault: vault_pda,
+                    lp_position: lp_position_pda,
+                    lp_mint: lp_mint_pda,
+                    owner_lp_account: get_associated_token_address(&payer.pubkey(), &lp_mint_pda),
+                    owner: payer.pubkey(),
+                    system_program: system_program::ID,
+                })
+                .args(zone::instruction::RequestWithdrawal {
+                    vault_num: *vault,
+                    shares: *shares,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send request withdrawal transaction")?;
+
+            config.sent(
+                "Successfully requested withdrawal",
+                &outcome,
+                &[
+                    ("vault", vault_pda),
+                    ("lp_mint", lp_mint_pda),
+                    ("lp_position", lp_position_pda),
+                ],
+            );
+        }
+        Commands::Withdraw { vault } => {
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
+            let (lp_mint_pda, _bump) =
+                Pubkey::find_program_address(&[b"lp_mint", vault_pda.as_ref()], &program_id);
+            let (lp_position_pda, _bump) = Pubkey::find_program_address(
+                &[b"lp_position", vault_pda.as_ref(), payer.pubkey().as_ref()],
+                &program_id,
+            );
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::Withdraw {
+                    vault: vault_pda,
+                    lp_position: lp_position_pda,
+                    lp_mint: lp_mint_pda,
+                    owner_lp_account: get_associated_token_address(&payer.pubkey(), &lp_mint_pda),
+                    owner: payer.pubkey(),
+                    token_program: anchor_spl::token::ID,
+                })
+                .args(zone::instruction::Withdraw { vault_num: *vault })
+                .send_or_simulate(&config)
+                .context("Failed to send withdraw transaction")?;
+
+            config.sent(
+                "Successfully withdrew",
+                &outcome,
+                &[
+                    ("vault", vault_pda),
+                    ("lp_mint", lp_mint_pda),
+                    ("lp_position", lp_position_pda),
+                ],
+            );
+        }
+        Commands::InitializeMarket {
+            token_address,
+            payout_multiplier,
+            title,
+            symbol,
+            decimals,
+            image_uri,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::InitializeMarket {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                    system_program: system_program::ID,
+                })
+                .args(zone::instruction::InitializeMarket {
+                    token_account,
+                    payout_multiplier: *payout_multiplier,
+                    metadata: title.as_ref().map(|title| zone::MarketMetadata {
+                        title: title.clone(),
+                        symbol: symbol.clone(),
+                        decimals: *decimals,
+                        image_uri: image_uri.clone(),
+                    }),
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send initialize market transaction")?;
+
+            config.sent(
+                "Successfully initialized market",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::UpdateMarketMetadata {
+            token_address,
+            title,
+            symbol,
+            decimals,
+            image_uri,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::UpdateMarketMetadata {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::UpdateMarketMetadata {
+                    metadata: zone::MarketMetadata {
+                        title: title.clone(),
+                        symbol: symbol.clone(),
+                        decimals: *decimals,
+                        image_uri: image_uri.clone(),
+                    },
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send update market metadata transaction")?;
+
+            config.sent(
+                "Successfully updated market metadata",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::StartMarket { token_address, end } => {
+            let token_account = parse_pubkey(token_address)?;
+
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let end = format!("{end} +0000");
+            let end = DateTime::parse_from_str(&end, "%Y-%m-%d %H:%M:%S %z")
+                .context("Invalid end, expected YYYY-MM-DD HH:MM:SS")?;
+            // let end = Utc::now() + chrono::Duration::days(1);
+
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+
+            let (price, publish) = current_price(&program, &config.price_source, &market)?;
+            if config.output == OutputFormat::Text {
+                println!("Current price {price}");
+            }
+
+            let outcome = publish
+                .into_iter()
+                .fold(program.request(), RequestBuilder::instruction)
+                .accounts(zone::accounts::StartMarket {
+                    market: market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                })
+                .args(zone::instruction::StartMarket {
+                    end: end.timestamp(),
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send start market transaction")?;
+
+            let accounts = [("market", market_pda)];
+            match config.output {
+                OutputFormat::Text => config.sent("Successfully start market", &outcome, &accounts),
+                OutputFormat::Json => {
+                    let mut report = config.sent_json(&outcome, &accounts);
+                    report["price"] = display::price_json(price);
+                    println!("{report}");
+                }
+            }
+        }
+        Commands::Bet {
+            token_address,
+            prediction,
+            amount,
+            bucket,
+            min_multiplier,
+            vault,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let prediction = *prediction != 0;
+
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+            let prediction_pda =
+                get_prediction_pda(&market_pda, market.start, &payer.pubkey(), &program_id);
+
+            let (price, publish) = current_price(&program, &config.price_source, &market)?;
+            if config.output == OutputFormat::Text {
+                println!("Current price {price}");
+            }
+
+            let outcome = publish
+                .into_iter()
+                .fold(program.request(), RequestBuilder::instruction)
+                .accounts(zone::accounts::CreatePrediction {
+                    prediction: prediction_pda,
+                    vault_exposure: get_vault_exposure_pda(
+                        &vault_pda,
+                        &market_pda,
+                        market.start,
+                        &program_id,
+                    ),
+                    user: payer.pubkey(),
+                    market: market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                    system_program: system_program::ID,
+                    vault: vault_pda,
+                })
+                .args(zone::instruction::CreatePrediction {
+                    prediction,
+                    bucket: *bucket,
+                    amount: *amount,
+                    min_multiplier: *min_multiplier,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send create prediction transaction")?;
+
+            let accounts = [
+                ("vault", vault_pda),
+                ("market", market_pda),
+                ("prediction", prediction_pda),
+            ];
+            match config.output {
+                OutputFormat::Text => {
+                    config.sent("Successfully create prediction", &outcome, &accounts)
+                }
+                OutputFormat::Json => {
+                    let mut report = config.sent_json(&outcome, &accounts);
+                    report["price"] = display::price_json(price);
+                    println!("{report}");
+                }
+            }
+        }
+        Commands::SetBuckets {
+            token_address,
+            bounds,
+            multipliers,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetBuckets {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetBuckets {
+                    bounds: bounds.clone(),
+                    multipliers: multipliers.clone(),
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set buckets transaction")?;
+
+            config.sent(
+                "Successfully set buckets",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::SetMoveThreshold {
+            token_address,
+            move_threshold,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetMoveThreshold {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetMoveThreshold {
+                    move_threshold: *move_threshold,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set move threshold transaction")?;
+
+            config.sent(
+                "Successfully set move threshold",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::SetDynamicOdds {
+            token_address,
+            odds_liquidity,
+            max_multiplier,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetDynamicOdds {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetDynamicOdds {
+                    odds_liquidity: *odds_liquidity,
+                    max_multiplier: *max_multiplier,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set dynamic odds transaction")?;
+
+            config.sent(
+                "Successfully set dynamic odds",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::InitializePriceFeed { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (price_feed_pda, _bump) = Pubkey::find_program_address(
+                &[
+                    b"price_feed",
+                    token_account.as_ref(),
+                    payer.pubkey().as_ref(),
+                ],
+                &program_id,
+            );
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::InitializePriceFeed {
+                    price_feed: price_feed_pda,
+                    authority: payer.pubkey(),
+                    system_program: system_program::ID,
+                })
+                .args(zone::instruction::InitializePriceFeed { token_account })
+                .send_or_simulate(&config)
+                .context("Failed to send initialize price feed transaction")?;
+
+            config.sent(
+                "Successfully initialized price feed",
+                &outcome,
+                &[("price_feed", price_feed_pda)],
+            );
+        }
+        Commands::UpdatePriceFeed {
+            token_address,
+            price,
+            conf,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (price_feed_pda, _bump) = Pubkey::find_program_address(
+                &[
+                    b"price_feed",
+                    token_account.as_ref(),
+                    payer.pubkey().as_ref(),
+                ],
+                &program_id,
+            );
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::UpdatePriceFeed {
+                    price_feed: price_feed_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::UpdatePriceFeed {
+                    price: price.mantissa,
+                    conf: conf.scaled_to(price.expo) as u64,
+                    expo: price.expo,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send update price feed transaction")?;
+
+            config.sent(
+                "Successfully updated price feed",
+                &outcome,
+                &[("price_feed", price_feed_pda)],
+            );
+        }
+        Commands::SetOracle {
+            token_address,
+            max_staleness,
+            max_confidence,
+            fallback_oracle,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+            let (price_feed_pda, _bump) = Pubkey::find_program_address(
+                &[
+                    b"price_feed",
+                    token_account.as_ref(),
+                    payer.pubkey().as_ref(),
+                ],
+                &program_id,
+            );
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetOracle {
+                    market: market_pda,
+                    oracle: price_feed_pda,
+                    fallback_oracle: fallback_oracle.as_deref().map(parse_pubkey).transpose()?,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetOracle {
+                    max_staleness: *max_staleness,
+                    max_confidence: *max_confidence,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set oracle transaction")?;
+
+            config.sent(
+                "Successfully set oracle",
+                &outcome,
+                &[("market", market_pda), ("price_feed", price_feed_pda)],
+            );
+        }
+        Commands::SetCashOutSpread {
+            token_address,
+            cash_out_spread,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetCashOutSpread {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetCashOutSpread {
+                    cash_out_spread: *cash_out_spread,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set cash out spread transaction")?;
+
+            config.sent(
+                "Successfully set cash out spread",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::Settle { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+            let prediction_pda =
+                get_prediction_pda(&market_pda, market.start, &payer.pubkey(), &program_id);
+            let prediction: zone::Prediction = program
+                .account(prediction_pda)
+                .context("Failed to fetch prediction account")?;
+
+            let now = Utc::now().timestamp();
+            if !market.started || market.end > now {
+                let end = display::time(market.end);
+                bail!("Market has not finished yet, it ends at {end}");
+            } else if !market.resolved {
+                bail!("Market is not resolved yet, resolve it with `resolve-market`");
+            } else if !market.finalized {
+                let dispute_end = display::time(market.resolved_at + market.dispute_period);
+                bail!("Final price can be disputed until {dispute_end}, finalize it after that");
+            }
+
+            let win = market.is_winning(&prediction, market.final_price);
+            if config.output == OutputFormat::Text {
+                if win {
+                    println!(
+                        "Final price {}: you win {}",
+                        market.final_price,
+                        display::sol(prediction.payout())
+                    );
+                } else {
+                    // Settling a loss only closes the prediction, nothing more is taken
+                    println!(
+                        "Final price {}: you lose your stake of {}",
+                        market.final_price,
+                        display::sol(prediction.amount)
+                    );
+                }
+            }
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SettlePrediction {
+                    prediction: prediction_pda,
+                    vault_exposure: get_vault_exposure_pda(
+                        &prediction.vault,
+                        &market_pda,
+                        market.start,
+                        &program_id,
+                    ),
+                    user: payer.pubkey(),
+                    market: market_pda,
+                    vault: prediction.vault,
+                })
+                .args(zone::instruction::SettlePrediction {})
+                .send_or_simulate(&config)
+                .context("Failed to send settle prediction transaction")?;
+
+            let accounts = [("market", market_pda), ("prediction", prediction_pda)];
+            match config.output {
+                OutputFormat::Text => config.sent("Successfully settled", &outcome, &accounts),
+                OutputFormat::Json => {
+                    let mut report = config.sent_json(&outcome, &accounts);
+                    report["final_price"] = display::price_json(market.final_price);
+                    report["win"] = json!(win);
+                    report["payout"] = json!(if win { prediction.payout() } else { 0 });
+                    println!("{report}");
+                }
+            }
+        }
+        Commands::CashOut { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+            let prediction_pda =
+                get_prediction_pda(&market_pda, market.start, &payer.pubkey(), &program_id);
+            let prediction: zone::Prediction = program
+                .account(prediction_pda)
+                .context("Failed to fetch prediction account")?;
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::CashOut {
+                    prediction: prediction_pda,
+                    vault_exposure: get_vault_exposure_pda(
+                        &prediction.vault,
+                        &market_pda,
+                        market.start,
+                        &program_id,
+                    ),
+                    user: payer.pubkey(),
+                    market: market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                    vault: prediction.vault,
+                })
+                .args(zone::instruction::CashOut {})
+                .send_or_simulate(&config)
+                .context("Failed to send cash out transaction")?;
+
+            config.sent(
+                "Successfully cashed out",
+                &outcome,
+                &[("market", market_pda), ("prediction", prediction_pda)],
+            );
+        }
+        Commands::CloseMarket { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::CloseMarket {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::CloseMarket {})
+                .send_or_simulate(&config)
+                .context("Failed to send close market transaction")?;
+
+            config.sent(
+                "Successfully closed market",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::MigrateMarket { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::MigrateMarket {
+                    market: market_pda,
+                    payer: payer.pubkey(),
+                    system_program: system_program::ID,
+                })
+                .args(zone::instruction::MigrateMarket {})
+                .send_or_simulate(&config)
+                .context("Failed to send migrate market transaction")?;
+
+            config.sent(
+                "Successfully migrated market",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::MigratePrediction {
+            token_address,
+            user,
+            expo,
+            vault,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let user = match user {
+                Some(user) => parse_pubkey(user)?,
+                None => payer.pubkey(),
+            };
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+            // Legacy predictions are at the address from before seeds included the market start
+            let (prediction_pda, _bump) = Pubkey::find_program_address(
+                &[b"prediction", market_pda.as_ref(), user.as_ref()],
+                &program_id,
+            );
+
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+            let (vault_pda, _vault) = fetch_vault(&program, *vault)?;
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::MigratePrediction {
+                    prediction: prediction_pda,
+                    market: market_pda,
+                    vault: vault_pda,
+                    vault_exposure: get_vault_exposure_pda(
+                        &vault_pda,
+                        &market_pda,
+                        market.start,
+                        &program_id,
+                    ),
+                    authority: payer.pubkey(),
+                    payer: payer.pubkey(),
+                    system_program: system_program::ID,
+                })
+                .args(zone::instruction::MigratePrediction {
+                    market_price_expo: *expo,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send migrate prediction transaction")?;
+
+            config.sent(
+                "Successfully migrated prediction",
+                &outcome,
+                &[("market", market_pda), ("prediction", prediction_pda)],
+            );
+        }
+        Commands::SetTwapWindow {
+            token_address,
+            twap_window,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetTwapWindow {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetTwapWindow {
+                    twap_window: *twap_window,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set TWAP window transaction")?;
+
+            config.sent(
+                "Successfully set TWAP window",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::SamplePrice { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SamplePrice {
+                    market: market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                })
+                .args(zone::instruction::SamplePrice {})
+                .send_or_simulate(&config)
+                .context("Failed to send sample price transaction")?;
+
+            config.sent(
+                "Successfully sampled price",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::ResolveMarket { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let market: zone::Market = program
+                .account(market_pda)
+                .context("Failed to fetch market account")?;
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::ResolveMarket {
+                    market: market_pda,
+                    oracle: market.oracle,
+                    fallback_oracle: market.fallback_oracle(),
+                })
+                .args(zone::instruction::ResolveMarket {})
+                .send_or_simulate(&config)
+                .context("Failed to send resolve market transaction")?;
+
+            config.sent(
+                "Successfully resolved market",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::SetDisputePeriod {
+            token_address,
+            dispute_period,
+            resolver,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::SetDisputePeriod {
+                    market: market_pda,
+                    authority: payer.pubkey(),
+                })
+                .args(zone::instruction::SetDisputePeriod {
+                    dispute_period: *dispute_period,
+                    resolver: parse_pubkey(resolver)?,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send set dispute period transaction")?;
+
+            config.sent(
+                "Successfully set dispute period",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::DisputeResolution {
+            token_address,
+            corrected_price,
+        } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::DisputeResolution {
+                    market: market_pda,
+                    resolver: payer.pubkey(),
+                })
+                .args(zone::instruction::DisputeResolution {
+                    corrected_price: *corrected_price,
+                })
+                .send_or_simulate(&config)
+                .context("Failed to send dispute resolution transaction")?;
+
+            config.sent(
+                "Successfully disputed resolution",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::FinalizeResolution { token_address } => {
+            let token_account = parse_pubkey(token_address)?;
+            let (market_pda, _bump) =
+                Pubkey::find_program_address(&[b"market", token_account.as_ref()], &program_id);
+
+            let outcome = program
+                .request()
+                .accounts(zone::accounts::FinalizeResolution { market: market_pda })
+                .args(zone::instruction::FinalizeResolution {})
+                .send_or_simulate(&config)
+                .context("Failed to send finalize resolution transaction")?;
+
+            config.sent(
+                "Successfully finalized resolution",
+                &outcome,
+                &[("market", market_pda)],
+            );
+        }
+        Commands::Keeper {
+            interval,
+            duration,
+            token_address,
+            batch_size,
+            retries,
+            once,
+        } => {
+            let market = match token_address {
+                Some(token_address) => {
+                    let token_account = parse_pubkey(token_address)?;
+                    let (market_pda, _bump) = Pubkey::find_program_address(
+                        &[b"market", token_account.as_ref()],
+                        &program_id,
+                    );
+                    Some(market_pda)
+                }
+                None => None,
+            };
+
+            let keeper = Keeper {
+                program: &program,
+                config: &config,
+                duration: *duration,
+                market,
+                batch_size: *batch_size,
+                retries: *retries,
+            };
+
+            keeper.run(Duration::from_secs(*interval), *once)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn get_vault_pda(vault_num: u8, program_id: &Pubkey) -> Pubkey {
+    let (vault_pda, _bump) = Pubkey::find_program_address(&[b"vault", &[vault_num]], program_id);
+
+    vault_pda
+}
+
+/// Vault `vault_num` and its address, failing if it was never initialized.
+fn fetch_vault(
+    program: &Program<&Keypair>,
+    vault_num: u8,
+) -> anyhow::Result<(Pubkey, zone::Vault)> {
+    let vault_pda = get_vault_pda(vault_num, &program.id());
+
+    let vault = program.account(vault_pda).with_context(|| {
+        format!("Vault {vault_num} does not exist, create it with `initialize --vault {vault_num}`")
+    })?;
+
+    Ok((vault_pda, vault))
+}
+
+fn parse_pubkey(address: &str) -> anyhow::Result<Pubkey> {
+    Pubkey::from_str(address).with_context(|| format!("Invalid address: {address}"))
+}
+
+/// Formats `used` lamports against a `limit` of 0 meaning none.
+fn utilization(used: u64, limit: u64) -> String {
+    let used_sol = used as f64 / LAMPORTS_PER_SOL as f64;
+
+    if limit == 0 {
+        format!("{used_sol} SOL (no limit)")
+    } else {
+        let limit_sol = limit as f64 / LAMPORTS_PER_SOL as f64;
+        let percent = used as f64 / limit as f64 * 100.0;
+        format!("{used_sol} / {limit_sol} SOL ({percent:.1}%)")
     }
 }
@@ -0,0 +1,241 @@
+use anchor_client::{
+    solana_client::{
+        client_error::ClientErrorKind,
+        rpc_request::{RpcError, RpcResponseErrorData},
+    },
+    solana_sdk::{instruction::InstructionError, transaction::TransactionError},
+    ClientError,
+};
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+use solana_program::pubkey::Pubkey;
+use zone::ZoneErrorCode;
+
+use crate::{
+    config::Config,
+    transaction::{Outcome, SimulationError},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Error returned by the program, decoded from its code.
+pub struct ProgramErrorInfo {
+    pub code: u32,
+    pub name: Option<String>,
+    pub message: Option<String>,
+}
+
+impl ProgramErrorInfo {
+    pub fn from_client_error(err: &ClientError) -> Option<ProgramErrorInfo> {
+        let ClientError::SolanaClientError(err) = err else {
+            return None;
+        };
+
+        let logs = match err.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+                ..
+            }) => result.logs.clone().unwrap_or_default(),
+            _ => vec![],
+        };
+
+        Self::from_transaction_error(&err.get_transaction_error()?, &logs)
+    }
+
+    pub fn from_transaction_error(
+        err: &TransactionError,
+        logs: &[String],
+    ) -> Option<ProgramErrorInfo> {
+        match err {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+                Some(Self::from_logs(*code, logs))
+            }
+            _ => None,
+        }
+    }
+
+    /// Names error `code` after its `ZoneErrorCode`. Other errors, like the ones of Anchor
+    /// constraints, are read from the line Anchor logs for them, like
+    /// `AnchorError occurred. Error Code: ConstraintHasOne. Error Number: 2001. Error Message: ...`.
+    pub fn from_logs(code: u32, logs: &[String]) -> ProgramErrorInfo {
+        let zone_error = code
+            .checked_sub(ERROR_CODE_OFFSET)
+            .and_then(|code| ZoneErrorCode::try_from(code).ok());
+        if let Some(zone_error) = zone_error {
+            return ProgramErrorInfo {
+                code,
+                name: Some(zone_error.name()),
+                message: Some(zone_error.to_string()),
+            };
+        }
+
+        let parsed = logs.iter().find_map(|log| {
+            let (_, rest) = log.split_once("AnchorError")?;
+            let (_, rest) = rest.split_once("Error Code: ")?;
+            let (name, rest) = rest.split_once(". Error Number: ")?;
+            let (number, message) = rest.split_once(". Error Message: ")?;
+            (number.parse() == Ok(code)).then(|| {
+                let message = message.strip_suffix('.').unwrap_or(message);
+                (name.to_string(), message.to_string())
+            })
+        });
+
+        ProgramErrorInfo {
+            code,
+            name: parsed.as_ref().map(|(name, _)| name.clone()),
+            message: parsed.map(|(_, message)| message),
+        }
+    }
+
+    /// Program error behind `err`, from a failed transaction or simulation.
+    pub fn from_error(err: &anyhow::Error) -> Option<ProgramErrorInfo> {
+        match err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<SimulationError>())
+        {
+            Some(simulation) => Self::from_transaction_error(
+                &simulation.err,
+                simulation.result.logs.as_deref().unwrap_or_default(),
+            ),
+            None => err
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<ClientError>())
+                .and_then(Self::from_client_error),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "code": self.code,
+            "name": self.name,
+            "message": self.message,
+        })
+    }
+}
+
+impl Config {
+    /// JSON object reporting a transaction sent or simulated with the given derived accounts.
+    pub fn sent_json(&self, outcome: &Outcome, accounts: &[(&str, Pubkey)]) -> Value {
+        let accounts: Map<String, Value> = accounts
+            .iter()
+            .map(|(name, pubkey)| (name.to_string(), json!(pubkey.to_string())))
+            .collect();
+
+        match outcome {
+            Outcome::Sent(sig) => json!({
+                "signature": sig.to_string(),
+                "explorer_url": self.explorer_url(sig),
+                "accounts": accounts,
+            }),
+            Outcome::Simulated(result) => json!({
+                "simulated": true,
+                "units_consumed": result.units_consumed,
+                "logs": result.logs,
+                "accounts": accounts,
+            }),
+        }
+    }
+
+    pub fn sent(&self, message: &str, outcome: &Outcome, accounts: &[(&str, Pubkey)]) {
+        match (self.output, outcome) {
+            (OutputFormat::Text, Outcome::Sent(sig)) => {
+                println!("{message}: {}", self.explorer_url(sig))
+            }
+            (OutputFormat::Text, Outcome::Simulated(result)) => {
+                print_logs(result.logs.as_deref().unwrap_or_default());
+                println!(
+                    "Simulation succeeded, {} compute units consumed",
+                    result.units_consumed.unwrap_or_default()
+                );
+            }
+            (OutputFormat::Json, _) => println!("{}", self.sent_json(outcome, accounts)),
+        }
+    }
+
+    /// Prints `text` or `json` depending on the output format.
+    pub fn show(&self, text: impl FnOnce(), json: impl FnOnce() -> Value) {
+        match self.output {
+            OutputFormat::Text => text(),
+            OutputFormat::Json => println!("{}", json()),
+        }
+    }
+}
+
+/// Prints the error the command failed with, with the name and message of the program
+/// error behind it if any.
+pub fn report_error(output: OutputFormat, err: &anyhow::Error) {
+    let simulation = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<SimulationError>());
+    let program_error = ProgramErrorInfo::from_error(err);
+
+    match output {
+        OutputFormat::Text => {
+            if let Some(simulation) = simulation {
+                print_logs(simulation.result.logs.as_deref().unwrap_or_default());
+            }
+
+            match program_error {
+                Some(ProgramErrorInfo {
+                    name: Some(name),
+                    message: Some(message),
+                    code,
+                }) => eprintln!("Error: {err}: {message} ({name}, error {code})"),
+                _ => eprintln!("Error: {err:#}"),
+            }
+        }
+        OutputFormat::Json => {
+            let mut report = json!({
+                "error": err.to_string(),
+                "details": format!("{err:#}"),
+                "program_error": program_error.map(|program_error| program_error.to_json()),
+            });
+            if let Some(simulation) = simulation {
+                report["simulated"] = json!(true);
+                report["units_consumed"] = json!(simulation.result.units_consumed);
+                report["logs"] = json!(simulation.result.logs);
+            }
+            println!("{report}");
+        }
+    }
+}
+
+fn print_logs(logs: &[String]) {
+    for log in logs {
+        eprintln!("  {log}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_error_from_code() {
+        let code = ERROR_CODE_OFFSET + ZoneErrorCode::AlreadyStarted as u32;
+        let info = ProgramErrorInfo::from_logs(code, &[]);
+        assert_eq!(info.name.as_deref(), Some("AlreadyStarted"));
+        assert_eq!(info.message.as_deref(), Some("Market has already started"));
+
+        let logs = [
+            "Program log: AnchorError caused by account: market. Error Code: \
+            ConstraintHasOne. Error Number: 2001. Error Message: A has one constraint was \
+            violated."
+                .to_string(),
+        ];
+        let info = ProgramErrorInfo::from_logs(2001, &logs);
+        assert_eq!(info.name.as_deref(), Some("ConstraintHasOne"));
+        assert_eq!(
+            info.message.as_deref(),
+            Some("A has one constraint was violated")
+        );
+
+        let info = ProgramErrorInfo::from_logs(2001, &[]);
+        assert!(info.name.is_none());
+    }
+}
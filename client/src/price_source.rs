@@ -0,0 +1,366 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use anchor_client::{
+    solana_sdk::{instruction::Instruction, signature::Keypair},
+    Program,
+};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+use zone::{Market, Price, PriceFeed};
+
+use crate::parse_price;
+
+/// Where to get the current price of a token.
+pub trait PriceSource {
+    fn price(&self, token_account: &Pubkey) -> anyhow::Result<Price>;
+}
+
+/// `--price-source`: the market oracle, an HTTP URL or a file of prices.
+#[derive(Clone)]
+pub enum PriceSourceKind {
+    Oracle,
+    Http(String),
+    File(PathBuf),
+}
+
+impl FromStr for PriceSourceKind {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        if source == "oracle" {
+            Ok(PriceSourceKind::Oracle)
+        } else if source.starts_with("http://") || source.starts_with("https://") {
+            Ok(PriceSourceKind::Http(source.to_string()))
+        } else {
+            Ok(PriceSourceKind::File(PathBuf::from(source)))
+        }
+    }
+}
+
+impl PriceSourceKind {
+    pub fn source<'a>(&self, program: &'a Program<&'a Keypair>) -> Box<dyn PriceSource + 'a> {
+        match self {
+            PriceSourceKind::Oracle => Box::new(OraclePrices { program }),
+            PriceSourceKind::Http(url) => Box::new(HttpPrices { url: url.clone() }),
+            PriceSourceKind::File(path) => Box::new(FilePrices { path: path.clone() }),
+        }
+    }
+}
+
+/// Price of the oracle of the market of the token, falling back the way the program does.
+pub struct OraclePrices<'a> {
+    pub program: &'a Program<&'a Keypair>,
+}
+
+impl PriceSource for OraclePrices<'_> {
+    fn price(&self, token_account: &Pubkey) -> anyhow::Result<Price> {
+        let (market_pda, _bump) =
+            Pubkey::find_program_address(&[b"market", token_account.as_ref()], &self.program.id());
+        let market: Market = self
+            .program
+            .account(market_pda)
+            .context("Failed to fetch market account")?;
+
+        oracle_price(self.program, &market)
+    }
+}
+
+/// Prices fetched from an HTTP API answering JSON with a `price` field, a decimal string or
+/// a number. `{token}` in the URL is replaced with the token address.
+pub struct HttpPrices {
+    pub url: String,
+}
+
+impl PriceSource for HttpPrices {
+    fn price(&self, token_account: &Pubkey) -> anyhow::Result<Price> {
+        let url = self.url.replace("{token}", &token_account.to_string());
+
+        let body = reqwest::blocking::get(&url)
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .with_context(|| format!("Failed to fetch price from {url}"))?;
+
+        response_price(&body).with_context(|| format!("Invalid price response from {url}"))
+    }
+}
+
+/// `price` field of a JSON response of the API.
+fn response_price(body: &str) -> anyhow::Result<Price> {
+    let response: Value = serde_json::from_str(body)?;
+
+    let price = match &response["price"] {
+        Value::String(price) => Some(price.clone()),
+        // Display of f64 never uses an exponent, unlike its JSON form
+        Value::Number(price) => price.as_f64().map(|price| price.to_string()),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("No price in response"))?;
+
+    parse_price(&price).map_err(|err| anyhow!(err))
+}
+
+/// Prices read from a file of `<token address> = "<price>"` lines. The file is read on
+/// every call, so tests can move prices while the keeper runs.
+pub struct FilePrices {
+    pub path: PathBuf,
+}
+
+impl PriceSource for FilePrices {
+    fn price(&self, token_account: &Pubkey) -> anyhow::Result<Price> {
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read prices from {}", self.path.display()))?;
+        let prices: toml::Value = toml::from_str(&content)
+            .with_context(|| format!("Invalid prices file {}", self.path.display()))?;
+
+        let price = prices
+            .get(token_account.to_string())
+            .and_then(|price| price.as_str())
+            .ok_or_else(|| anyhow!("No price for {token_account} in {}", self.path.display()))?;
+
+        parse_price(price).map_err(|err| anyhow!(err))
+    }
+}
+
+/// Current price of the market token, with the instruction publishing it to the market
+/// oracle when it comes from another source and this wallet publishes the oracle.
+/// Otherwise the price is the one of the oracle, which the program uses anyway.
+pub fn current_price(
+    program: &Program<&Keypair>,
+    source: &PriceSourceKind,
+    market: &Market,
+) -> anyhow::Result<(Price, Option<Instruction>)> {
+    if let PriceSourceKind::Oracle = source {
+        return Ok((oracle_price(program, market)?, None));
+    }
+
+    let price_feed: PriceFeed = program
+        .account(market.oracle)
+        .context("Failed to fetch oracle account")?;
+
+    let published = publish_price(
+        source.source(program).as_ref(),
+        market,
+        &price_feed,
+        &program.id(),
+        &program.payer(),
+    )?;
+    match published {
+        Some((price, instruction)) => Ok((price, Some(instruction))),
+        None => {
+            eprintln!(
+                "Warning: the oracle is published by {}, not this wallet, using its price instead of --price-source",
+                price_feed.authority
+            );
+            Ok((oracle_price(program, market)?, None))
+        }
+    }
+}
+
+/// Price of `source` with the instruction publishing it to `price_feed`, the oracle of the
+/// market, if `payer` publishes it. None otherwise, without asking `source`.
+fn publish_price(
+    source: &dyn PriceSource,
+    market: &Market,
+    price_feed: &PriceFeed,
+    program_id: &Pubkey,
+    payer: &Pubkey,
+) -> anyhow::Result<Option<(Price, Instruction)>> {
+    if price_feed.authority != *payer {
+        return Ok(None);
+    }
+
+    let price = source.price(&market.token_account)?;
+    let instruction = Instruction {
+        program_id: *program_id,
+        accounts: zone::accounts::UpdatePriceFeed {
+            price_feed: market.oracle,
+            authority: *payer,
+        }
+        .to_account_metas(None),
+        data: zone::instruction::UpdatePriceFeed {
+            price: price.mantissa,
+            conf: 0,
+            expo: price.expo,
+        }
+        .data(),
+    };
+
+    Ok(Some((price, instruction)))
+}
+
+fn oracle_price(program: &Program<&Keypair>, market: &Market) -> anyhow::Result<Price> {
+    let oracle: PriceFeed = program
+        .account(market.oracle)
+        .context("Failed to fetch oracle account")?;
+    let fallback_oracle: Option<PriceFeed> = match market.fallback_oracle() {
+        Some(fallback_oracle) => Some(
+            program
+                .account(fallback_oracle)
+                .context("Failed to fetch fallback oracle account")?,
+        ),
+        None => None,
+    };
+
+    market
+        .oracle_price(&oracle, fallback_oracle.as_ref(), Utc::now().timestamp())
+        .map_err(|err| anyhow!("No usable price from the market oracle: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use anchor_lang::{AccountDeserialize, Space};
+
+    use super::*;
+
+    fn market(token_account: Pubkey, oracle: Pubkey) -> Market {
+        let data = [0; 8 + Market::INIT_SPACE];
+        let mut market = Market::try_deserialize_unchecked(&mut &data[..]).unwrap();
+        market.token_account = token_account;
+        market.oracle = oracle;
+
+        market
+    }
+
+    fn price_feed(authority: Pubkey) -> PriceFeed {
+        PriceFeed {
+            version: zone::constants::ACCOUNT_VERSION,
+            authority,
+            token_account: Pubkey::new_unique(),
+            price: 100_000,
+            conf: 0,
+            expo: -5,
+            publish_time: 0,
+            reserved: [0; 64],
+        }
+    }
+
+    /// File of `prices` in the temp directory, removed when dropped.
+    struct PricesFile(PathBuf);
+
+    impl PricesFile {
+        fn new(prices: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("zone-prices-{}.toml", Pubkey::new_unique()));
+            fs::write(&path, prices).unwrap();
+
+            PricesFile(path)
+        }
+    }
+
+    impl Drop for PricesFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_price_source_kind_from_str() {
+        assert!(matches!(
+            PriceSourceKind::from_str("oracle"),
+            Ok(PriceSourceKind::Oracle)
+        ));
+        assert!(matches!(
+            PriceSourceKind::from_str("https://example.com/{token}"),
+            Ok(PriceSourceKind::Http(url)) if url == "https://example.com/{token}"
+        ));
+        assert!(matches!(
+            PriceSourceKind::from_str("http://localhost:8080"),
+            Ok(PriceSourceKind::Http(_))
+        ));
+        assert!(matches!(
+            PriceSourceKind::from_str("prices.toml"),
+            Ok(PriceSourceKind::File(path)) if path == Path::new("prices.toml")
+        ));
+    }
+
+    #[test]
+    fn test_response_price() {
+        let price = response_price(r#"{"price": "0.00002"}"#).unwrap();
+        assert_eq!((price.mantissa, price.expo), (2, -5));
+
+        let price = response_price(r#"{"price": 1.5}"#).unwrap();
+        assert_eq!((price.mantissa, price.expo), (15, -1));
+
+        // Small numbers are written with an exponent in JSON
+        let price = response_price(r#"{"price": 2e-5}"#).unwrap();
+        assert_eq!((price.mantissa, price.expo), (2, -5));
+
+        let price = response_price(r#"{"price": 12}"#).unwrap();
+        assert_eq!((price.mantissa, price.expo), (12, 0));
+
+        assert!(response_price(r#"{"value": "1.5"}"#).is_err());
+        assert!(response_price(r#"{"price": null}"#).is_err());
+        assert!(response_price(r#"{"price": "-1"}"#).is_err());
+        assert!(response_price("not json").is_err());
+    }
+
+    #[test]
+    fn test_file_prices() {
+        let token_account = Pubkey::new_unique();
+        let file = PricesFile::new(&format!("{token_account} = \"1.25\"\n"));
+        let prices = FilePrices {
+            path: file.0.clone(),
+        };
+
+        let price = prices.price(&token_account).unwrap();
+        assert_eq!((price.mantissa, price.expo), (125, -2));
+
+        assert!(prices.price(&Pubkey::new_unique()).is_err());
+
+        let missing = FilePrices {
+            path: file.0.with_extension("missing"),
+        };
+        assert!(missing.price(&token_account).is_err());
+    }
+
+    #[test]
+    fn test_publish_price() {
+        let token_account = Pubkey::new_unique();
+        let oracle = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let market = market(token_account, oracle);
+        let file = PricesFile::new(&format!("{token_account} = \"1.25\"\n"));
+        let prices = FilePrices {
+            path: file.0.clone(),
+        };
+
+        // Published by another wallet, the oracle price is used instead without reading prices
+        let missing = FilePrices {
+            path: file.0.with_extension("missing"),
+        };
+        let published = publish_price(
+            &missing,
+            &market,
+            &price_feed(Pubkey::new_unique()),
+            &program_id,
+            &payer,
+        )
+        .unwrap();
+        assert!(published.is_none());
+
+        let (price, instruction) =
+            publish_price(&prices, &market, &price_feed(payer), &program_id, &payer)
+                .unwrap()
+                .unwrap();
+        assert_eq!((price.mantissa, price.expo), (125, -2));
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts[0].pubkey, oracle);
+        assert_eq!(instruction.accounts[1].pubkey, payer);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(
+            instruction.data,
+            zone::instruction::UpdatePriceFeed {
+                price: 125,
+                conf: 0,
+                expo: -2,
+            }
+            .data()
+        );
+    }
+}
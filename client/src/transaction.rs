@@ -0,0 +1,119 @@
+use std::{fmt, str::FromStr};
+
+use anchor_client::{
+    solana_client::{rpc_client::RpcClient, rpc_response::RpcSimulateTransactionResult},
+    solana_sdk::{
+        compute_budget::ComputeBudgetInstruction, instruction::Instruction, signature::Keypair,
+        signature::Signature, transaction::TransactionError,
+    },
+    RequestBuilder,
+};
+use anyhow::Context;
+use solana_program::pubkey::Pubkey;
+
+use crate::config::Config;
+
+/// Price paid per compute unit to get transactions included ahead of others.
+#[derive(Clone, Copy)]
+pub enum PriorityFee {
+    MicroLamports(u64),
+    /// Estimated from the fees recently paid to write the same accounts
+    Auto,
+}
+
+impl FromStr for PriorityFee {
+    type Err = String;
+
+    fn from_str(fee: &str) -> Result<Self, Self::Err> {
+        match fee {
+            "auto" => Ok(PriorityFee::Auto),
+            _ => fee
+                .parse()
+                .map(PriorityFee::MicroLamports)
+                .map_err(|_| format!("Invalid priority fee: {fee}")),
+        }
+    }
+}
+
+/// What became of a transaction: sent, or only simulated with `--simulate`.
+pub enum Outcome {
+    Sent(Signature),
+    Simulated(RpcSimulateTransactionResult),
+}
+
+/// Simulation of the transaction failed, with the logs up to the failure.
+#[derive(Debug)]
+pub struct SimulationError {
+    pub err: TransactionError,
+    pub result: RpcSimulateTransactionResult,
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Simulation failed: {}", self.err)
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+pub trait SendOrSimulate {
+    /// Sends the transaction, or only simulates it if `config.simulate` is set.
+    fn send_or_simulate(self, config: &Config) -> anyhow::Result<Outcome>;
+}
+
+impl SendOrSimulate for RequestBuilder<'_, &Keypair> {
+    fn send_or_simulate(mut self, config: &Config) -> anyhow::Result<Outcome> {
+        let rpc = RpcClient::new_with_commitment(config.cluster.url(), config.commitment);
+
+        // Instructions added to the request run before the one of its `args`
+        if let Some(compute_limit) = config.compute_limit {
+            self = self.instruction(ComputeBudgetInstruction::set_compute_unit_limit(
+                compute_limit,
+            ));
+        }
+        if let Some(priority_fee) = config.priority_fee {
+            let micro_lamports = match priority_fee {
+                PriorityFee::MicroLamports(micro_lamports) => micro_lamports,
+                PriorityFee::Auto => estimate_priority_fee(&rpc, &self.instructions()?)?,
+            };
+            self = self.instruction(ComputeBudgetInstruction::set_compute_unit_price(
+                micro_lamports,
+            ));
+        }
+
+        if !config.simulate {
+            return Ok(Outcome::Sent(self.send()?));
+        }
+
+        let transaction = self.signed_transaction()?;
+        let result = rpc.simulate_transaction(&transaction)?.value;
+
+        match result.err.clone() {
+            Some(err) => Err(SimulationError { err, result }.into()),
+            None => Ok(Outcome::Simulated(result)),
+        }
+    }
+}
+
+/// 75th percentile of the priority fees paid in recent slots by transactions writing the
+/// accounts `instructions` write.
+fn estimate_priority_fee(rpc: &RpcClient, instructions: &[Instruction]) -> anyhow::Result<u64> {
+    let mut writable_accounts: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|instruction| &instruction.accounts)
+        .filter(|account| account.is_writable)
+        .map(|account| account.pubkey)
+        .collect();
+    writable_accounts.sort_unstable();
+    writable_accounts.dedup();
+
+    let mut fees: Vec<u64> = rpc
+        .get_recent_prioritization_fees(&writable_accounts)
+        .context("Failed to fetch recent prioritization fees")?
+        .into_iter()
+        .map(|fee| fee.prioritization_fee)
+        .collect();
+    fees.sort_unstable();
+
+    Ok(fees.get(fees.len() * 3 / 4).copied().unwrap_or(0))
+}
@@ -1,4 +1,9 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, system_program, Discriminator};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Mint, Token, TokenAccount},
+};
+use constants::{MAX_BUCKETS, MAX_IMAGE_URI_LEN, MAX_SYMBOL_LEN, MAX_TITLE_LEN, TWAP_SAMPLES};
 
 declare_id!("7UyLYeoNwWeh3LgMUnWFKPc1Ebwr8Afzsz8hVjgavoRa");
 // declare_id!("2BqXsVFG5Woo6VVg6pK4RM7g6W7YZwCSM9wYou8kzu6F");
@@ -7,19 +12,49 @@ pub mod constants {
     pub const VAULT_SEED: &[u8] = b"vault";
     pub const MARKET_SEED: &[u8] = b"market";
     pub const PREDICTION_SEED: &[u8] = b"prediction";
+    pub const PRICE_FEED_SEED: &[u8] = b"price_feed";
+    pub const LP_MINT_SEED: &[u8] = b"lp_mint";
+    pub const LP_POSITION_SEED: &[u8] = b"lp_position";
+    pub const VAULT_EXPOSURE_SEED: &[u8] = b"vault_exposure";
+
+    pub const MAX_BUCKETS: usize = 8;
+    pub const TWAP_SAMPLES: usize = 16;
+    pub const BASIS_POINTS: i64 = 10_000;
+    pub const MAX_EXPO: i32 = 18;
+    pub const WITHDRAWAL_CLAIM_WINDOW: i64 = 24 * 60 * 60;
+
+    pub const ACCOUNT_VERSION: u8 = 1;
+
+    pub const MAX_TITLE_LEN: usize = 64;
+    pub const MAX_SYMBOL_LEN: usize = 16;
+    pub const MAX_IMAGE_URI_LEN: usize = 200;
 }
 
 #[program]
 pub mod zone {
     use anchor_lang::{
         context::{Context, CpiContext},
-        system_program, Key, ToAccountInfo,
+        system_program, AccountSerialize, Key, Space, ToAccountInfo,
     };
-    use solana_program::{clock::Clock, msg, pubkey::Pubkey, sysvar::Sysvar};
+    use anchor_spl::token::{self, Burn, MintTo};
+    use solana_program::{clock::Clock, msg, pubkey::Pubkey, rent::Rent, sysvar::Sysvar};
+
+    use anchor_lang::emit;
 
     use crate::{
-        CreatePrediction, Initialize, InitializeMarket, SettlePrediction, StartMarket,
-        ZoneErrorCode,
+        constants::{
+            ACCOUNT_VERSION, BASIS_POINTS, MAX_BUCKETS, MAX_EXPO, TWAP_SAMPLES, VAULT_SEED,
+            WITHDRAWAL_CLAIM_WINDOW,
+        },
+        CashOut, CloseMarket, CreatePrediction, Deposit, DisputeResolution, FinalizeResolution,
+        Initialize, InitializeLpMint, InitializeMarket, InitializePriceFeed, LegacyMarket,
+        LegacyPrediction, Market, MarketKind, MarketMetadata, MigrateMarket, MigratePrediction,
+        Prediction, PredictionStatus, Price, RequestWithdrawal, ResolutionDisputed,
+        ResolutionFinalized, ResolutionProposed, ResolveMarket, SamplePrice, SetBuckets,
+        SetCashOutSpread, SetDisputePeriod, SetDynamicOdds, SetMoveThreshold, SetOracle,
+        SetRiskLimits, SetTwapWindow, SetWithdrawalDelay, SettleLosingPrediction, SettlePrediction,
+        SettleWinningPrediction, StartMarket, TwapSample, UpdateMarketMetadata, UpdatePriceFeed,
+        Withdraw, ZoneErrorCode,
     };
 
     pub fn initialize(
@@ -27,7 +62,13 @@ pub mod zone {
         vault_num: u8,
         amount: u64,
     ) -> anchor_lang::Result<()> {
-        let _vault_num = vault_num;
+        let vault = &mut ctx.accounts.vault;
+        vault.version = ACCOUNT_VERSION;
+        vault.authority = ctx.accounts.authority.key();
+        vault.vault_num = vault_num;
+        vault.bump = ctx.bumps.vault;
+        vault.deposits = amount;
+
         // deposit funds to vault
         let cpi_context = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
@@ -41,20 +82,457 @@ pub mod zone {
         Ok(())
     }
 
+    pub fn initialize_lp_mint(
+        ctx: Context<InitializeLpMint>,
+        vault_num: u8,
+    ) -> anchor_lang::Result<()> {
+        msg!("Initialize LP mint");
+
+        // Funds already in the vault belong to its authority
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(vault_info.data_len());
+        let shares = ctx.accounts.vault.nav(vault_info.lamports(), rent_exempt);
+
+        let bump = [ctx.bumps.vault];
+        let vault_num = [vault_num];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_num, &bump]];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: ctx.accounts.authority_lp_account.to_account_info(),
+                authority: ctx.accounts.vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::mint_to(cpi_context, shares)?;
+
+        Ok(())
+    }
+
+    pub fn set_withdrawal_delay(
+        ctx: Context<SetWithdrawalDelay>,
+        vault_num: u8,
+        withdrawal_delay: i64,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set withdrawal delay of vault {}", vault_num);
+
+        if withdrawal_delay < 0 {
+            return Err(ZoneErrorCode::InvalidWithdrawalDelay.into());
+        }
+
+        ctx.accounts.vault.withdrawal_delay = withdrawal_delay;
+
+        Ok(())
+    }
+
+    pub fn set_risk_limits(
+        ctx: Context<SetRiskLimits>,
+        vault_num: u8,
+        max_market_liability: u64,
+        max_total_liability: u64,
+        max_imbalance: u64,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set risk limits of vault {}", vault_num);
+
+        let vault = &mut ctx.accounts.vault;
+
+        vault.max_market_liability = max_market_liability;
+        vault.max_total_liability = max_total_liability;
+        vault.max_imbalance = max_imbalance;
+
+        Ok(())
+    }
+
+    pub fn deposit(ctx: Context<Deposit>, vault_num: u8, amount: u64) -> anchor_lang::Result<()> {
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(vault_info.data_len());
+        let nav = ctx.accounts.vault.nav(vault_info.lamports(), rent_exempt);
+        let supply = ctx.accounts.lp_mint.supply;
+
+        // Price shares on NAV so depositing doesn't dilute existing shares
+        let shares = if supply == 0 {
+            amount
+        } else if nav == 0 {
+            return Err(ZoneErrorCode::VaultInsolvent.into());
+        } else {
+            (amount as u128 * supply as u128 / nav as u128) as u64
+        };
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.depositor.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, amount)?;
+
+        let bump = [ctx.bumps.vault];
+        let vault_num = [vault_num];
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &vault_num, &bump]];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: ctx.accounts.depositor_lp_account.to_account_info(),
+                authority: ctx.accounts.vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::mint_to(cpi_context, shares)?;
+
+        ctx.accounts.vault.deposits += amount;
+
+        msg!("Minted {} LP shares", shares);
+
+        Ok(())
+    }
+
+    pub fn request_withdrawal(
+        ctx: Context<RequestWithdrawal>,
+        vault_num: u8,
+        shares: u64,
+    ) -> anchor_lang::Result<()> {
+        let clock = Clock::get()?;
+
+        if shares == 0 || shares > ctx.accounts.owner_lp_account.amount {
+            return Err(ZoneErrorCode::InsufficientShares.into());
+        }
+
+        let lp_position = &mut ctx.accounts.lp_position;
+        lp_position.version = ACCOUNT_VERSION;
+        lp_position.owner = ctx.accounts.owner.key();
+        lp_position.vault = ctx.accounts.vault.key();
+        lp_position.pending_shares = shares;
+        lp_position.requested_at = clock.unix_timestamp;
+
+        msg!(
+            "Requested withdrawal of {} LP shares from vault {}",
+            shares,
+            vault_num
+        );
+
+        Ok(())
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, vault_num: u8) -> anchor_lang::Result<()> {
+        let lp_position = &mut ctx.accounts.lp_position;
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+
+        if lp_position.pending_shares == 0 {
+            return Err(ZoneErrorCode::NoPendingWithdrawal.into());
+        }
+
+        // LPs can't run from predictions that are still open, nor hold on to an old request
+        // to do so whenever they like
+        if vault.open_predictions > 0 {
+            let unlocked_at = lp_position.requested_at + vault.withdrawal_delay;
+
+            if clock.unix_timestamp < unlocked_at {
+                return Err(ZoneErrorCode::WithdrawalLocked.into());
+            }
+
+            if clock.unix_timestamp > unlocked_at + WITHDRAWAL_CLAIM_WINDOW {
+                return Err(ZoneErrorCode::WithdrawalExpired.into());
+            }
+        }
+
+        let shares = lp_position.pending_shares;
+        if shares > ctx.accounts.owner_lp_account.amount {
+            return Err(ZoneErrorCode::InsufficientShares.into());
+        }
+
+        let vault_info = vault.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(vault_info.data_len());
+        let nav = vault.nav(vault_info.lamports(), rent_exempt);
+        let amount = (shares as u128 * nav as u128 / ctx.accounts.lp_mint.supply as u128) as u64;
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.owner_lp_account.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        token::burn(cpi_context, shares)?;
+
+        lp_position.pending_shares = 0;
+        vault.deposits = vault.deposits.saturating_sub(amount);
+
+        **ctx
+            .accounts
+            .vault
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .owner
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        msg!("Withdrew {} lamports from vault {}", amount, vault_num);
+
+        Ok(())
+    }
+
     pub fn initialize_market(
         ctx: Context<InitializeMarket>,
         token_account: Pubkey,
         payout_multiplier: u64,
+        metadata: Option<MarketMetadata>,
     ) -> anchor_lang::Result<()> {
         msg!("Initialize market");
 
         let market = &mut ctx.accounts.market;
 
+        market.version = ACCOUNT_VERSION;
         market.authority = ctx.accounts.authority.key();
         market.token_account = token_account;
         market.payout_multiplier = payout_multiplier;
         market.started = false;
 
+        if let Some(metadata) = metadata {
+            metadata.validate()?;
+            market.metadata = metadata;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_market_metadata(
+        ctx: Context<UpdateMarketMetadata>,
+        metadata: MarketMetadata,
+    ) -> anchor_lang::Result<()> {
+        msg!("Update market metadata");
+
+        let market = &mut ctx.accounts.market;
+
+        if market.started {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        metadata.validate()?;
+        market.metadata = metadata;
+
+        Ok(())
+    }
+
+    pub fn set_buckets(
+        ctx: Context<SetBuckets>,
+        bounds: Vec<i64>,
+        multipliers: Vec<u64>,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set buckets");
+
+        let market = &mut ctx.accounts.market;
+
+        if market.started {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        // N buckets are separated by N - 1 ascending bounds (in basis points)
+        if multipliers.len() < 2
+            || multipliers.len() > MAX_BUCKETS
+            || bounds.len() != multipliers.len() - 1
+            || bounds.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(ZoneErrorCode::InvalidBucketConfig.into());
+        }
+
+        market.kind = MarketKind::Range;
+        market.bucket_count = multipliers.len() as u8;
+        market.bucket_bounds = [0; MAX_BUCKETS];
+        market.bucket_bounds[..bounds.len()].copy_from_slice(&bounds);
+        market.bucket_multipliers = [0; MAX_BUCKETS];
+        market.bucket_multipliers[..multipliers.len()].copy_from_slice(&multipliers);
+
+        Ok(())
+    }
+
+    pub fn set_move_threshold(
+        ctx: Context<SetMoveThreshold>,
+        move_threshold: u64,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set move threshold");
+
+        let market = &mut ctx.accounts.market;
+
+        if market.started {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        if move_threshold == 0 {
+            return Err(ZoneErrorCode::InvalidMoveThreshold.into());
+        }
+
+        market.kind = MarketKind::PercentageMove;
+        market.move_threshold = move_threshold;
+
+        Ok(())
+    }
+
+    pub fn set_dynamic_odds(
+        ctx: Context<SetDynamicOdds>,
+        odds_liquidity: u64,
+        max_multiplier: u64,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set dynamic odds");
+
+        let market = &mut ctx.accounts.market;
+
+        if market.started {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        if max_multiplier < 100 {
+            return Err(ZoneErrorCode::InvalidMaxMultiplier.into());
+        }
+
+        market.odds_liquidity = odds_liquidity;
+        market.max_multiplier = max_multiplier;
+
+        Ok(())
+    }
+
+    pub fn initialize_price_feed(
+        ctx: Context<InitializePriceFeed>,
+        token_account: Pubkey,
+    ) -> anchor_lang::Result<()> {
+        msg!("Initialize price feed");
+
+        let price_feed = &mut ctx.accounts.price_feed;
+
+        price_feed.version = ACCOUNT_VERSION;
+        price_feed.authority = ctx.accounts.authority.key();
+        price_feed.token_account = token_account;
+
+        Ok(())
+    }
+
+    pub fn update_price_feed(
+        ctx: Context<UpdatePriceFeed>,
+        price: u64,
+        conf: u64,
+        expo: i32,
+    ) -> anchor_lang::Result<()> {
+        let price_feed = &mut ctx.accounts.price_feed;
+        let clock = Clock::get()?;
+
+        if expo.unsigned_abs() > MAX_EXPO as u32 {
+            return Err(ZoneErrorCode::InvalidExponent.into());
+        }
+
+        price_feed.price = price;
+        price_feed.conf = conf;
+        price_feed.expo = expo;
+        price_feed.publish_time = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    pub fn set_oracle(
+        ctx: Context<SetOracle>,
+        max_staleness: i64,
+        max_confidence: u64,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set oracle");
+
+        let market = &mut ctx.accounts.market;
+
+        // Migrated markets are already started without an oracle, and can only resolve once given one
+        if market.started && (market.resolved || market.oracle != Pubkey::default()) {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        if ctx.accounts.oracle.token_account != market.token_account {
+            return Err(ZoneErrorCode::InvalidOracle.into());
+        }
+
+        if max_staleness <= 0 || max_confidence == 0 {
+            return Err(ZoneErrorCode::InvalidOracleLimits.into());
+        }
+
+        market.oracle = ctx.accounts.oracle.key();
+        market.max_staleness = max_staleness;
+        market.max_confidence = max_confidence;
+        market.fallback_oracle = match &ctx.accounts.fallback_oracle {
+            Some(fallback_oracle) => {
+                if fallback_oracle.token_account != market.token_account {
+                    return Err(ZoneErrorCode::InvalidOracle.into());
+                }
+                fallback_oracle.key()
+            }
+            None => Pubkey::default(),
+        };
+
+        Ok(())
+    }
+
+    pub fn set_cash_out_spread(
+        ctx: Context<SetCashOutSpread>,
+        cash_out_spread: u64,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set cash out spread");
+
+        let market = &mut ctx.accounts.market;
+
+        if market.started {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        if cash_out_spread > BASIS_POINTS as u64 {
+            return Err(ZoneErrorCode::InvalidCashOutSpread.into());
+        }
+
+        market.cash_out_spread = cash_out_spread;
+
+        Ok(())
+    }
+
+    pub fn set_twap_window(
+        ctx: Context<SetTwapWindow>,
+        twap_window: i64,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set TWAP window");
+
+        let market = &mut ctx.accounts.market;
+
+        if market.started {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        if twap_window < 0 {
+            return Err(ZoneErrorCode::InvalidTwapWindow.into());
+        }
+
+        market.twap_window = twap_window;
+
+        Ok(())
+    }
+
+    pub fn set_dispute_period(
+        ctx: Context<SetDisputePeriod>,
+        dispute_period: i64,
+        resolver: Pubkey,
+    ) -> anchor_lang::Result<()> {
+        msg!("Set dispute period");
+
+        let market = &mut ctx.accounts.market;
+
+        if market.started {
+            return Err(ZoneErrorCode::AlreadyStarted.into());
+        }
+
+        if dispute_period < 0 {
+            return Err(ZoneErrorCode::InvalidDisputePeriod.into());
+        }
+
+        market.dispute_period = dispute_period;
+        market.resolver = resolver;
+
         Ok(())
     }
 
@@ -68,6 +546,13 @@ pub mod zone {
             return Err(ZoneErrorCode::AlreadyStarted.into());
         }
 
+        // Only open the market while its oracle is live
+        market.oracle_price(
+            &ctx.accounts.oracle,
+            ctx.accounts.fallback_oracle.as_deref(),
+            clock.unix_timestamp,
+        )?;
+
         market.started = true;
         market.start = clock.unix_timestamp;
         market.end = end;
@@ -78,22 +563,88 @@ pub mod zone {
     pub fn create_prediction(
         ctx: Context<CreatePrediction>,
         prediction: bool,
+        bucket: Option<u8>,
         amount: u64,
-        current_price: u64,
+        min_multiplier: u64,
     ) -> anchor_lang::Result<()> {
         let market = &mut ctx.accounts.market;
         let clock = Clock::get()?;
 
-        if market.start > clock.unix_timestamp {
+        if !market.started {
             return Err(ZoneErrorCode::NotStarted.into());
         }
 
+        if clock.unix_timestamp >= market.end || market.resolved {
+            return Err(ZoneErrorCode::AlreadyFinished.into());
+        }
+
+        let bucket = match (market.kind, bucket) {
+            (MarketKind::HigherLower | MarketKind::PercentageMove, None) => 0,
+            (MarketKind::Range, Some(bucket)) if bucket < market.bucket_count => bucket,
+            _ => return Err(ZoneErrorCode::InvalidBucket.into()),
+        };
+
+        let current_price = market.oracle_price(
+            &ctx.accounts.oracle,
+            ctx.accounts.fallback_oracle.as_deref(),
+            clock.unix_timestamp,
+        )?;
+
+        // Lock in the odds offered right now, rejecting them if worse than the bettor accepts
+        let payout_multiplier = match market.kind {
+            MarketKind::HigherLower | MarketKind::PercentageMove => {
+                market.quote_multiplier(prediction, amount)
+            }
+            MarketKind::Range => market.bucket_multipliers[bucket as usize],
+        };
+
+        if payout_multiplier < min_multiplier {
+            return Err(ZoneErrorCode::SlippageExceeded.into());
+        }
+
+        let payout = (amount * payout_multiplier) / 100;
+
+        if market.kind != MarketKind::Range {
+            if prediction {
+                market.high_exposure += amount;
+            } else {
+                market.low_exposure += amount;
+            }
+        }
+        market.liability += payout;
+        market.open_predictions += 1;
+
+        let vault_exposure = &mut ctx.accounts.vault_exposure;
+        vault_exposure.version = ACCOUNT_VERSION;
+        vault_exposure.vault = ctx.accounts.vault.key();
+        vault_exposure.market = market.key();
+        vault_exposure.liability += payout;
+        if market.kind != MarketKind::Range {
+            if prediction {
+                vault_exposure.high_exposure += amount;
+            } else {
+                vault_exposure.low_exposure += amount;
+            }
+        }
+
+        ctx.accounts
+            .vault
+            .check_risk_limits(vault_exposure, payout)?;
+
         let new_prediction = &mut ctx.accounts.prediction;
+        new_prediction.version = ACCOUNT_VERSION;
         new_prediction.user = ctx.accounts.user.key();
         new_prediction.market = ctx.accounts.market.key();
         new_prediction.prediction = prediction;
+        new_prediction.bucket = bucket;
         new_prediction.amount = amount;
         new_prediction.market_price = current_price;
+        new_prediction.payout_multiplier = payout_multiplier;
+        new_prediction.vault = ctx.accounts.vault.key();
+
+        let vault = &mut ctx.accounts.vault;
+        vault.liabilities += payout;
+        vault.open_predictions += 1;
 
         // Transfer the amount to the market escrow account
         let cpi_context = CpiContext::new(
@@ -108,52 +659,469 @@ pub mod zone {
         Ok(())
     }
 
-    pub fn settle_prediction(
-        ctx: Context<SettlePrediction>,
-        actual_price: u64,
-    ) -> anchor_lang::Result<()> {
+    pub fn sample_price(ctx: Context<SamplePrice>) -> anchor_lang::Result<()> {
         let market = &mut ctx.accounts.market;
         let clock = Clock::get()?;
 
-        if market.end > clock.unix_timestamp {
-            return Err(ZoneErrorCode::NotFinished.into());
-        } else {
-            let prediction = &mut ctx.accounts.prediction;
-            let reward = (prediction.amount * market.payout_multiplier) / 100;
+        if market.twap_window == 0
+            || clock.unix_timestamp < market.end - market.twap_window
+            || clock.unix_timestamp > market.end
+        {
+            return Err(ZoneErrorCode::OutsideTwapWindow.into());
+        }
 
-            if (actual_price > prediction.market_price && prediction.prediction)
-                || (actual_price < prediction.market_price && !prediction.prediction)
-            {
-                **ctx
-                    .accounts
-                    .vault
-                    .to_account_info()
-                    .try_borrow_mut_lamports()? -= reward;
-                **ctx
-                    .accounts
-                    .user
-                    .to_account_info()
-                    .try_borrow_mut_lamports()? += reward;
-            } else {
-                let cpi_context = CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    system_program::Transfer {
-                        from: ctx.accounts.user.to_account_info(),
-                        to: ctx.accounts.vault.to_account_info(),
-                    },
-                );
-                system_program::transfer(cpi_context, reward)?;
+        let price = market.oracle_price(
+            &ctx.accounts.oracle,
+            ctx.accounts.fallback_oracle.as_deref(),
+            clock.unix_timestamp,
+        )?;
+
+        // Space samples out so the ring buffer covers the whole window
+        if let Some(last) = market.last_twap_sample() {
+            let min_interval = market.twap_window / TWAP_SAMPLES as i64;
+            if clock.unix_timestamp - last.timestamp < min_interval.max(1) {
+                return Err(ZoneErrorCode::SampleTooSoon.into());
             }
         }
 
+        let index = market.twap_sample_count as usize % TWAP_SAMPLES;
+        market.twap_samples[index] = TwapSample {
+            price,
+            timestamp: clock.unix_timestamp,
+        };
+        market.twap_sample_count += 1;
+
         Ok(())
     }
+
+    pub fn resolve_market(ctx: Context<ResolveMarket>) -> anchor_lang::Result<()> {
+        msg!("Resolve market");
+
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        if !market.started || market.end > clock.unix_timestamp {
+            return Err(ZoneErrorCode::NotFinished.into());
+        }
+
+        if market.resolved {
+            return Err(ZoneErrorCode::AlreadyResolved.into());
+        }
+
+        // TWAP markets nobody sampled settle on the spot price like the others
+        let twap = (market.twap_window > 0).then(|| market.twap()).flatten();
+        let final_price = match twap {
+            Some(twap) => twap,
+            // Only prices published around the end count, not whenever resolution runs
+            None => market.oracle_price(
+                &ctx.accounts.oracle,
+                ctx.accounts.fallback_oracle.as_deref(),
+                market.end,
+            )?,
+        };
+
+        market.final_price = final_price;
+        market.resolved = true;
+        market.resolved_at = clock.unix_timestamp;
+
+        emit!(ResolutionProposed {
+            market: market.key(),
+            final_price,
+            dispute_end: market.resolved_at + market.dispute_period,
+        });
+
+        // Nothing to wait for without a dispute period
+        if market.dispute_period == 0 {
+            market.finalized = true;
+
+            emit!(ResolutionFinalized {
+                market: market.key(),
+                final_price,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn dispute_resolution(
+        ctx: Context<DisputeResolution>,
+        corrected_price: Price,
+    ) -> anchor_lang::Result<()> {
+        msg!("Dispute resolution");
+
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        if !market.resolved {
+            return Err(ZoneErrorCode::NotResolved.into());
+        }
+
+        if market.finalized || clock.unix_timestamp >= market.resolved_at + market.dispute_period {
+            return Err(ZoneErrorCode::DisputeWindowClosed.into());
+        }
+
+        if corrected_price.mantissa == 0 {
+            return Err(ZoneErrorCode::InvalidPrice.into());
+        }
+
+        if corrected_price.expo.unsigned_abs() > MAX_EXPO as u32 {
+            return Err(ZoneErrorCode::InvalidExponent.into());
+        }
+
+        emit!(ResolutionDisputed {
+            market: market.key(),
+            resolver: ctx.accounts.resolver.key(),
+            proposed_price: market.final_price,
+            corrected_price,
+        });
+
+        market.final_price = corrected_price;
+
+        Ok(())
+    }
+
+    pub fn finalize_resolution(ctx: Context<FinalizeResolution>) -> anchor_lang::Result<()> {
+        msg!("Finalize resolution");
+
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        if !market.resolved {
+            return Err(ZoneErrorCode::NotResolved.into());
+        }
+
+        if market.finalized {
+            return Err(ZoneErrorCode::AlreadyFinalized.into());
+        }
+
+        if clock.unix_timestamp < market.resolved_at + market.dispute_period {
+            return Err(ZoneErrorCode::DisputeWindowOpen.into());
+        }
+
+        market.finalized = true;
+
+        emit!(ResolutionFinalized {
+            market: market.key(),
+            final_price: market.final_price,
+        });
+
+        Ok(())
+    }
+
+    pub fn settle_prediction(ctx: Context<SettlePrediction>) -> anchor_lang::Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        if market.end > clock.unix_timestamp {
+            return Err(ZoneErrorCode::NotFinished.into());
+        } else if !market.resolved {
+            return Err(ZoneErrorCode::NotResolved.into());
+        } else if !market.finalized {
+            return Err(ZoneErrorCode::NotFinalized.into());
+        } else {
+            let prediction = &mut ctx.accounts.prediction;
+
+            if prediction.status != PredictionStatus::Open {
+                return Err(ZoneErrorCode::PredictionClosed.into());
+            }
+            prediction.status = PredictionStatus::Settled;
+
+            let reward = prediction.payout();
+            market.liability -= reward;
+            market.open_predictions = market.open_predictions.saturating_sub(1);
+            ctx.accounts.vault_exposure.release(reward);
+
+            let vault = &mut ctx.accounts.vault;
+            vault.liabilities -= reward;
+            vault.open_predictions -= 1;
+
+            if market.is_winning(prediction, market.final_price) {
+                vault.realized_pnl += prediction.amount as i64 - reward as i64;
+
+                **ctx
+                    .accounts
+                    .vault
+                    .to_account_info()
+                    .try_borrow_mut_lamports()? -= reward;
+                **ctx
+                    .accounts
+                    .user
+                    .to_account_info()
+                    .try_borrow_mut_lamports()? += reward;
+            } else {
+                // The vault already holds the stake, the same as `settle_losing_prediction`
+                vault.realized_pnl += prediction.amount as i64;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pays a winning prediction out to its user without their signature, so keepers can
+    /// settle on their behalf. Losing predictions go through `settle_losing_prediction`.
+    pub fn settle_winning_prediction(
+        ctx: Context<SettleWinningPrediction>,
+    ) -> anchor_lang::Result<()> {
+        let market = &mut ctx.accounts.market;
+        let prediction = &mut ctx.accounts.prediction;
+        let clock = Clock::get()?;
+
+        if market.end > clock.unix_timestamp {
+            return Err(ZoneErrorCode::NotFinished.into());
+        } else if !market.resolved {
+            return Err(ZoneErrorCode::NotResolved.into());
+        } else if !market.finalized {
+            return Err(ZoneErrorCode::NotFinalized.into());
+        }
+
+        if prediction.status != PredictionStatus::Open {
+            return Err(ZoneErrorCode::PredictionClosed.into());
+        }
+
+        if !market.is_winning(prediction, market.final_price) {
+            return Err(ZoneErrorCode::PredictionLost.into());
+        }
+        prediction.status = PredictionStatus::Settled;
+
+        let reward = prediction.payout();
+        market.liability -= reward;
+        market.open_predictions = market.open_predictions.saturating_sub(1);
+        ctx.accounts.vault_exposure.release(reward);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.liabilities -= reward;
+        vault.open_predictions -= 1;
+        vault.realized_pnl += prediction.amount as i64 - reward as i64;
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= reward;
+        **ctx.accounts.user.try_borrow_mut_lamports()? += reward;
+
+        Ok(())
+    }
+
+    /// Closes a losing prediction without the signature of its user, so keepers can release
+    /// the liability it holds in the vault. The vault keeps the stake, nothing is transferred.
+    pub fn settle_losing_prediction(
+        ctx: Context<SettleLosingPrediction>,
+    ) -> anchor_lang::Result<()> {
+        let market = &mut ctx.accounts.market;
+        let prediction = &mut ctx.accounts.prediction;
+        let clock = Clock::get()?;
+
+        if market.end > clock.unix_timestamp {
+            return Err(ZoneErrorCode::NotFinished.into());
+        } else if !market.resolved {
+            return Err(ZoneErrorCode::NotResolved.into());
+        } else if !market.finalized {
+            return Err(ZoneErrorCode::NotFinalized.into());
+        }
+
+        if prediction.status != PredictionStatus::Open {
+            return Err(ZoneErrorCode::PredictionClosed.into());
+        }
+
+        if market.is_winning(prediction, market.final_price) {
+            return Err(ZoneErrorCode::PredictionWon.into());
+        }
+        prediction.status = PredictionStatus::Settled;
+
+        let reward = prediction.payout();
+        market.liability -= reward;
+        market.open_predictions = market.open_predictions.saturating_sub(1);
+        ctx.accounts.vault_exposure.release(reward);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.liabilities -= reward;
+        vault.open_predictions -= 1;
+        vault.realized_pnl += prediction.amount as i64;
+
+        Ok(())
+    }
+
+    pub fn cash_out(ctx: Context<CashOut>) -> anchor_lang::Result<()> {
+        let market = &mut ctx.accounts.market;
+        let prediction = &mut ctx.accounts.prediction;
+        let clock = Clock::get()?;
+
+        if market.end <= clock.unix_timestamp {
+            return Err(ZoneErrorCode::AlreadyFinished.into());
+        }
+
+        if prediction.status != PredictionStatus::Open {
+            return Err(ZoneErrorCode::PredictionClosed.into());
+        }
+
+        let price = market.oracle_price(
+            &ctx.accounts.oracle,
+            ctx.accounts.fallback_oracle.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        let value = market.cash_out_value(prediction, price, clock.unix_timestamp);
+
+        prediction.status = PredictionStatus::CashedOut;
+
+        market.liability -= prediction.payout();
+        market.open_predictions = market.open_predictions.saturating_sub(1);
+        ctx.accounts.vault_exposure.release(prediction.payout());
+
+        let vault = &mut ctx.accounts.vault;
+        vault.liabilities -= prediction.payout();
+        vault.open_predictions -= 1;
+        vault.realized_pnl += prediction.amount as i64 - value as i64;
+
+        if market.kind != MarketKind::Range {
+            let vault_exposure = &mut ctx.accounts.vault_exposure;
+            if prediction.prediction {
+                market.high_exposure -= prediction.amount;
+                vault_exposure.high_exposure -= prediction.amount;
+            } else {
+                market.low_exposure -= prediction.amount;
+                vault_exposure.low_exposure -= prediction.amount;
+            }
+        }
+
+        **ctx
+            .accounts
+            .vault
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= value;
+        **ctx
+            .accounts
+            .user
+            .to_account_info()
+            .try_borrow_mut_lamports()? += value;
+
+        msg!("Cashed out {} lamports", value);
+
+        Ok(())
+    }
+
+    pub fn close_market(ctx: Context<CloseMarket>) -> anchor_lang::Result<()> {
+        msg!("Close market");
+
+        let market = &ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        if market.started && market.end > clock.unix_timestamp {
+            return Err(ZoneErrorCode::NotFinished.into());
+        }
+
+        // Markets migrated from before the counter only track open predictions by liability
+        if market.open_predictions > 0 || market.liability > 0 {
+            return Err(ZoneErrorCode::OpenPredictions.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn migrate_market(ctx: Context<MigrateMarket>) -> anchor_lang::Result<()> {
+        msg!("Migrate market");
+
+        let market_info = ctx.accounts.market.to_account_info();
+        let legacy = LegacyMarket::load(&market_info)?;
+        let market = Market::from(legacy);
+
+        crate::realloc_account(
+            &market_info,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            8 + Market::INIT_SPACE,
+        )?;
+        market.try_serialize(&mut &mut market_info.try_borrow_mut_data()?[..])?;
+
+        Ok(())
+    }
+
+    /// Legacy predictions recorded the market price as a bare integer and weren't tied to a
+    /// vault, so the market authority tells the exponent of the price and the vault backing
+    /// the prediction, which takes on its payout.
+    pub fn migrate_prediction(
+        ctx: Context<MigratePrediction>,
+        market_price_expo: i32,
+    ) -> anchor_lang::Result<()> {
+        msg!("Migrate prediction");
+
+        if market_price_expo.unsigned_abs() > MAX_EXPO as u32 {
+            return Err(ZoneErrorCode::InvalidExponent.into());
+        }
+
+        let prediction_info = ctx.accounts.prediction.to_account_info();
+        let legacy = LegacyPrediction::load(&prediction_info)?;
+        let market = &mut ctx.accounts.market;
+        if legacy.market != market.key() {
+            return Err(anchor_lang::error::ErrorCode::ConstraintHasOne.into());
+        }
+
+        let prediction = legacy.migrate(
+            market_price_expo,
+            market.payout_multiplier,
+            ctx.accounts.vault.key(),
+        );
+        let payout = prediction.payout();
+
+        if prediction.prediction {
+            market.high_exposure += prediction.amount;
+        } else {
+            market.low_exposure += prediction.amount;
+        }
+        market.liability += payout;
+        market.open_predictions += 1;
+
+        let vault_exposure = &mut ctx.accounts.vault_exposure;
+        vault_exposure.version = ACCOUNT_VERSION;
+        vault_exposure.vault = ctx.accounts.vault.key();
+        vault_exposure.market = market.key();
+        vault_exposure.liability += payout;
+        if prediction.prediction {
+            vault_exposure.high_exposure += prediction.amount;
+        } else {
+            vault_exposure.low_exposure += prediction.amount;
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        vault.liabilities += payout;
+        vault.open_predictions += 1;
+
+        crate::realloc_account(
+            &prediction_info,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            8 + Prediction::INIT_SPACE,
+        )?;
+        prediction.try_serialize(&mut &mut prediction_info.try_borrow_mut_data()?[..])?;
+
+        Ok(())
+    }
+}
+
+/// Grows `account` to `space` bytes, topping up its rent exemption from `payer`.
+fn realloc_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+) -> Result<()> {
+    let rent_exempt = Rent::get()?.minimum_balance(space);
+    if rent_exempt > account.lamports() {
+        let cpi_context = CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: payer.to_account_info(),
+                to: account.clone(),
+            },
+        );
+        system_program::transfer(cpi_context, rent_exempt - account.lamports())?;
+    }
+
+    account.realloc(space, false)?;
+
+    Ok(())
 }
 
 #[derive(Accounts)]
 #[instruction(vault_num: u8)]
 pub struct Initialize<'info> {
-    #[account(init, seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump, payer = authority, space = 8 + 1)]
+    #[account(init, seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump, payer = authority, space = 8 + Vault::INIT_SPACE)]
     vault: Account<'info, Vault>,
 
     #[account(mut)]
@@ -163,102 +1131,1427 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(token_account: Pubkey)]
-pub struct InitializeMarket<'info> {
+#[instruction(vault_num: u8)]
+pub struct InitializeLpMint<'info> {
+    #[account(seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump, has_one = authority)]
+    vault: Account<'info, Vault>,
+
     #[account(
         init,
-        seeds = [crate::constants::MARKET_SEED, token_account.as_ref()],
+        seeds = [crate::constants::LP_MINT_SEED, vault.key().as_ref()],
         bump,
         payer = authority,
-        space = 8 + std::mem::size_of::<Market>())
+        mint::decimals = 9,
+        mint::authority = vault)
     ]
-    market: Account<'info, Market>,
+    lp_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = lp_mint,
+        associated_token::authority = authority)
+    ]
+    authority_lp_account: Account<'info, TokenAccount>,
 
     #[account(mut)]
     authority: Signer<'info>,
 
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
     system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct StartMarket<'info> {
-    #[account(mut)]
-    market: Account<'info, Market>,
+#[instruction(vault_num: u8)]
+pub struct SetWithdrawalDelay<'info> {
+    #[account(mut, seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump, has_one = authority)]
+    vault: Account<'info, Vault>,
+
+    authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct CreatePrediction<'info> {
-    #[account(mut)]
+#[instruction(vault_num: u8)]
+pub struct SetRiskLimits<'info> {
+    #[account(mut, seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump, has_one = authority)]
+    vault: Account<'info, Vault>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_num: u8)]
+pub struct Deposit<'info> {
+    #[account(mut, seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump)]
     vault: Account<'info, Vault>,
 
+    #[account(mut, seeds = [crate::constants::LP_MINT_SEED, vault.key().as_ref()], bump)]
+    lp_mint: Account<'info, Mint>,
+
     #[account(
-        init,
-        seeds = [crate::constants::PREDICTION_SEED, market.key().as_ref(), user.key.as_ref()],
-        bump,
-        payer = user,
-        space = 8 + std::mem::size_of::<Prediction>())
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = lp_mint,
+        associated_token::authority = depositor)
     ]
-    prediction: Account<'info, Prediction>,
+    depositor_lp_account: Account<'info, TokenAccount>,
 
     #[account(mut)]
-    user: Signer<'info>,
-
-    #[account(mut)]
-    market: Account<'info, Market>,
+    depositor: Signer<'info>,
 
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
     system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SettlePrediction<'info> {
-    #[account(mut)]
+#[instruction(vault_num: u8)]
+pub struct RequestWithdrawal<'info> {
+    #[account(seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump)]
     vault: Account<'info, Vault>,
 
-    #[account(mut)]
-    prediction: Account<'info, Prediction>,
+    #[account(
+        init_if_needed,
+        seeds = [crate::constants::LP_POSITION_SEED, vault.key().as_ref(), owner.key.as_ref()],
+        bump,
+        payer = owner,
+        space = 8 + LiquidityPosition::INIT_SPACE)
+    ]
+    lp_position: Account<'info, LiquidityPosition>,
 
-    #[account(mut)]
-    user: Signer<'info>,
+    #[account(seeds = [crate::constants::LP_MINT_SEED, vault.key().as_ref()], bump)]
+    lp_mint: Account<'info, Mint>,
+
+    #[account(associated_token::mint = lp_mint, associated_token::authority = owner)]
+    owner_lp_account: Account<'info, TokenAccount>,
 
     #[account(mut)]
-    market: Account<'info, Market>,
+    owner: Signer<'info>,
 
     system_program: Program<'info, System>,
 }
 
-#[account]
-pub struct Vault {}
+#[derive(Accounts)]
+#[instruction(vault_num: u8)]
+pub struct Withdraw<'info> {
+    #[account(mut, seeds = [crate::constants::VAULT_SEED, &[vault_num]], bump)]
+    vault: Account<'info, Vault>,
 
-#[account]
-pub struct Market {
-    authority: Pubkey,
-    token_account: Pubkey,
-    started: bool,
-    start: i64,
-    end: i64,
-    payout_multiplier: u64, // Multiplier for payout (e.g., 200 for 2x)
-}
+    #[account(
+        mut,
+        seeds = [crate::constants::LP_POSITION_SEED, vault.key().as_ref(), owner.key.as_ref()],
+        bump,
+        has_one = owner,
+        has_one = vault)
+    ]
+    lp_position: Account<'info, LiquidityPosition>,
 
-#[account]
-pub struct Prediction {
-    user: Pubkey,
-    market: Pubkey,
-    prediction: bool,  // True for higher, False for lower
-    market_price: u64, // Market price at prediction time
-    amount: u64,       // Amount wagered
-}
+    #[account(mut, seeds = [crate::constants::LP_MINT_SEED, vault.key().as_ref()], bump)]
+    lp_mint: Account<'info, Mint>,
 
-#[error_code]
-pub enum ZoneErrorCode {
-    #[msg("Market has already started")]
-    AlreadyStarted,
+    #[account(mut, associated_token::mint = lp_mint, associated_token::authority = owner)]
+    owner_lp_account: Account<'info, TokenAccount>,
 
-    #[msg("Market has not started yet")]
-    NotStarted,
+    #[account(mut)]
+    owner: Signer<'info>,
 
-    #[msg("Market has not finished yet")]
-    NotFinished,
+    token_program: Program<'info, Token>,
+}
 
-    #[msg("Not enough SOL")]
+#[derive(Accounts)]
+#[instruction(token_account: Pubkey)]
+pub struct InitializeMarket<'info> {
+    #[account(
+        init,
+        seeds = [crate::constants::MARKET_SEED, token_account.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + Market::INIT_SPACE)
+    ]
+    market: Account<'info, Market>,
+
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketMetadata<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetBuckets<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMoveThreshold<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDynamicOdds<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_account: Pubkey)]
+pub struct InitializePriceFeed<'info> {
+    #[account(
+        init,
+        seeds = [crate::constants::PRICE_FEED_SEED, token_account.as_ref(), authority.key.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + PriceFeed::INIT_SPACE)
+    ]
+    price_feed: Account<'info, PriceFeed>,
+
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePriceFeed<'info> {
+    #[account(mut, has_one = authority)]
+    price_feed: Account<'info, PriceFeed>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOracle<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    oracle: Account<'info, PriceFeed>,
+
+    fallback_oracle: Option<Account<'info, PriceFeed>>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCashOutSpread<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTwapWindow<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDisputePeriod<'info> {
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct StartMarket<'info> {
+    #[account(mut, has_one = oracle @ ZoneErrorCode::InvalidOracle)]
+    market: Account<'info, Market>,
+
+    oracle: Account<'info, PriceFeed>,
+
+    #[account(address = market.fallback_oracle @ ZoneErrorCode::InvalidOracle)]
+    fallback_oracle: Option<Account<'info, PriceFeed>>,
+}
+
+#[derive(Accounts)]
+pub struct CreatePrediction<'info> {
+    #[account(mut)]
+    vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        seeds = [crate::constants::PREDICTION_SEED, market.key().as_ref(), user.key.as_ref(), &market.start.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + Prediction::INIT_SPACE)
+    ]
+    prediction: Account<'info, Prediction>,
+
+    #[account(
+        init_if_needed,
+        seeds = [crate::constants::VAULT_EXPOSURE_SEED, vault.key().as_ref(), market.key().as_ref(), &market.start.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + VaultExposure::INIT_SPACE)
+    ]
+    vault_exposure: Account<'info, VaultExposure>,
+
+    #[account(mut)]
+    user: Signer<'info>,
+
+    #[account(mut, has_one = oracle @ ZoneErrorCode::InvalidOracle)]
+    market: Account<'info, Market>,
+
+    oracle: Account<'info, PriceFeed>,
+
+    #[account(address = market.fallback_oracle @ ZoneErrorCode::InvalidOracle)]
+    fallback_oracle: Option<Account<'info, PriceFeed>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettlePrediction<'info> {
+    #[account(mut)]
+    vault: Account<'info, Vault>,
+
+    #[account(mut, has_one = vault, has_one = market, has_one = user)]
+    prediction: Account<'info, Prediction>,
+
+    #[account(
+        mut,
+        seeds = [crate::constants::VAULT_EXPOSURE_SEED, vault.key().as_ref(), market.key().as_ref(), &market.start.to_le_bytes()],
+        bump)
+    ]
+    vault_exposure: Account<'info, VaultExposure>,
+
+    #[account(mut)]
+    user: Signer<'info>,
+
+    #[account(mut)]
+    market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct SettleWinningPrediction<'info> {
+    #[account(mut)]
+    vault: Account<'info, Vault>,
+
+    #[account(mut, has_one = vault, has_one = market, has_one = user)]
+    prediction: Account<'info, Prediction>,
+
+    #[account(
+        mut,
+        seeds = [crate::constants::VAULT_EXPOSURE_SEED, vault.key().as_ref(), market.key().as_ref(), &market.start.to_le_bytes()],
+        bump)
+    ]
+    vault_exposure: Account<'info, VaultExposure>,
+
+    /// CHECK: Only credited with the payout, checked against the prediction
+    #[account(mut)]
+    user: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct SettleLosingPrediction<'info> {
+    #[account(mut)]
+    vault: Account<'info, Vault>,
+
+    #[account(mut, has_one = vault, has_one = market)]
+    prediction: Account<'info, Prediction>,
+
+    #[account(
+        mut,
+        seeds = [crate::constants::VAULT_EXPOSURE_SEED, vault.key().as_ref(), market.key().as_ref(), &market.start.to_le_bytes()],
+        bump)
+    ]
+    vault_exposure: Account<'info, VaultExposure>,
+
+    #[account(mut)]
+    market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct SamplePrice<'info> {
+    #[account(mut, has_one = oracle @ ZoneErrorCode::InvalidOracle)]
+    market: Account<'info, Market>,
+
+    oracle: Account<'info, PriceFeed>,
+
+    #[account(address = market.fallback_oracle @ ZoneErrorCode::InvalidOracle)]
+    fallback_oracle: Option<Account<'info, PriceFeed>>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveMarket<'info> {
+    #[account(mut, has_one = oracle @ ZoneErrorCode::InvalidOracle)]
+    market: Account<'info, Market>,
+
+    oracle: Account<'info, PriceFeed>,
+
+    #[account(address = market.fallback_oracle @ ZoneErrorCode::InvalidOracle)]
+    fallback_oracle: Option<Account<'info, PriceFeed>>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeResolution<'info> {
+    #[account(mut)]
+    market: Account<'info, Market>,
+
+    #[account(
+        constraint = resolver.key() == market.resolver || resolver.key() == market.authority
+            @ ZoneErrorCode::NotResolver
+    )]
+    resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeResolution<'info> {
+    #[account(mut)]
+    market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct CashOut<'info> {
+    #[account(mut)]
+    vault: Account<'info, Vault>,
+
+    #[account(mut, has_one = user, has_one = market, has_one = vault)]
+    prediction: Account<'info, Prediction>,
+
+    #[account(
+        mut,
+        seeds = [crate::constants::VAULT_EXPOSURE_SEED, vault.key().as_ref(), market.key().as_ref(), &market.start.to_le_bytes()],
+        bump)
+    ]
+    vault_exposure: Account<'info, VaultExposure>,
+
+    #[account(mut)]
+    user: Signer<'info>,
+
+    #[account(mut, has_one = oracle @ ZoneErrorCode::InvalidOracle)]
+    market: Account<'info, Market>,
+
+    oracle: Account<'info, PriceFeed>,
+
+    #[account(address = market.fallback_oracle @ ZoneErrorCode::InvalidOracle)]
+    fallback_oracle: Option<Account<'info, PriceFeed>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(mut, has_one = authority, close = authority)]
+    market: Account<'info, Market>,
+
+    #[account(mut)]
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarket<'info> {
+    /// CHECK: Still on the legacy layout, checked when loading it
+    #[account(mut, owner = crate::ID)]
+    market: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePrediction<'info> {
+    /// CHECK: Still on the legacy layout, checked when loading it
+    #[account(mut, owner = crate::ID)]
+    prediction: UncheckedAccount<'info>,
+
+    #[account(mut, has_one = authority)]
+    market: Account<'info, Market>,
+
+    #[account(mut, has_one = authority)]
+    vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        seeds = [crate::constants::VAULT_EXPOSURE_SEED, vault.key().as_ref(), market.key().as_ref(), &market.start.to_le_bytes()],
+        bump,
+        payer = payer,
+        space = 8 + VaultExposure::INIT_SPACE)
+    ]
+    vault_exposure: Account<'info, VaultExposure>,
+
+    authority: Signer<'info>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Vault {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub vault_num: u8,
+    pub bump: u8,
+    pub liabilities: u64, // Total payout owed to open predictions if they all win
+    pub open_predictions: u64, // Number of predictions not settled or cashed out yet
+    pub withdrawal_delay: i64, // Seconds LPs wait to withdraw while predictions are open
+    pub max_market_liability: u64, // Cap on the liability of the vault in a market, 0 for none
+    pub max_total_liability: u64, // Cap on `liabilities`, 0 for none
+    pub max_imbalance: u64, // Cap on the HIGH and LOW exposure difference of the vault in a market, 0 for none
+    pub deposits: u64,      // SOL put in by the authority and LPs, less withdrawals
+    pub realized_pnl: i64,  // Stakes kept less payouts of closed predictions
+    pub reserved: [u8; 64], // Room for new fields
+}
+
+impl Vault {
+    /// Net asset value backing the LP shares: the balance above rent exemption less
+    /// what open predictions could still be paid.
+    pub fn nav(&self, lamports: u64, rent_exempt: u64) -> u64 {
+        lamports
+            .saturating_sub(rent_exempt)
+            .saturating_sub(self.liabilities)
+    }
+
+    /// Rejects a prediction adding `payout` to the liabilities, once `vault_exposure`
+    /// accounts for it.
+    pub fn check_risk_limits(&self, vault_exposure: &VaultExposure, payout: u64) -> Result<()> {
+        if self.max_market_liability > 0 && vault_exposure.liability > self.max_market_liability {
+            return Err(ZoneErrorCode::MarketLiabilityExceeded.into());
+        }
+
+        if self.max_total_liability > 0 && self.liabilities + payout > self.max_total_liability {
+            return Err(ZoneErrorCode::TotalLiabilityExceeded.into());
+        }
+
+        if self.max_imbalance > 0 && vault_exposure.imbalance() > self.max_imbalance {
+            return Err(ZoneErrorCode::ImbalanceExceeded.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Share of a market's book backed by a vault, which its `max_market_liability` and
+/// `max_imbalance` apply to.
+#[account]
+#[derive(InitSpace)]
+pub struct VaultExposure {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub market: Pubkey,
+    pub liability: u64, // Payout owed to open predictions in the market if all win
+    pub high_exposure: u64, // Amount wagered on HIGH backed by the vault
+    pub low_exposure: u64, // Amount wagered on LOW backed by the vault
+    pub reserved: [u8; 16], // Room for new fields
+}
+
+impl VaultExposure {
+    /// Difference between the amounts wagered on each side backed by the vault.
+    pub fn imbalance(&self) -> u64 {
+        self.high_exposure.abs_diff(self.low_exposure)
+    }
+
+    /// Takes the payout of a closed prediction off the liability.
+    pub fn release(&mut self, payout: u64) {
+        self.liability = self.liability.saturating_sub(payout);
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LiquidityPosition {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub vault: Pubkey,
+    pub pending_shares: u64, // LP shares requested for withdrawal
+    pub requested_at: i64,   // Unix timestamp the withdrawal was requested at
+    pub reserved: [u8; 64],  // Room for new fields
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub token_account: Pubkey,
+    pub started: bool,
+    pub start: i64,
+    pub end: i64,
+    pub payout_multiplier: u64, // Multiplier for payout (e.g., 200 for 2x)
+    pub kind: MarketKind,
+    pub bucket_count: u8,
+    pub bucket_bounds: [i64; MAX_BUCKETS], // Ascending bounds between buckets, in basis points
+    pub bucket_multipliers: [u64; MAX_BUCKETS], // Multiplier for payout of each bucket
+    pub move_threshold: u64, // Minimum price move in either direction, in basis points
+    pub odds_liquidity: u64, // Virtual liquidity damping the odds curve, 0 for fixed odds
+    pub max_multiplier: u64, // Upper bound of the multiplier offered by the odds curve
+    pub high_exposure: u64,  // Total amount wagered on HIGH
+    pub low_exposure: u64,   // Total amount wagered on LOW
+    pub liability: u64,      // Total payout owed to open predictions if they all win
+    pub oracle: Pubkey,      // Price feed used to price the market
+    pub fallback_oracle: Pubkey, // Price feed used when the oracle is stale or uncertain, default if none
+    pub max_staleness: i64,      // Oldest oracle price accepted, in seconds
+    pub max_confidence: u64, // Widest confidence interval accepted, in basis points of the price
+    pub cash_out_spread: u64, // Haircut on the fair value of cashed out positions, in basis points
+    pub twap_window: i64,    // Seconds before end averaged into the final price, 0 for spot
+    pub twap_samples: [TwapSample; TWAP_SAMPLES], // Ring buffer of oracle samples taken during the window
+    pub twap_sample_count: u32,
+    pub resolved: bool,      // Final price has been proposed
+    pub final_price: Price,  // Price the market settles at
+    pub resolved_at: i64,    // Unix timestamp the final price was proposed at
+    pub dispute_period: i64, // Seconds the final price can be corrected for after resolution
+    pub resolver: Pubkey,    // Allowed to correct the final price, besides the authority
+    pub finalized: bool, // Final price can no longer be corrected and predictions can be settled
+    pub metadata: MarketMetadata,
+    pub open_predictions: u64, // Predictions not settled or cashed out yet
+    pub reserved: [u8; 56],    // Room for new fields
+}
+
+impl Market {
+    pub fn fallback_oracle(&self) -> Option<Pubkey> {
+        (self.fallback_oracle != Pubkey::default()).then_some(self.fallback_oracle)
+    }
+
+    /// Price of `oracle` at `time`, or of `fallback_oracle` when the former is stale or
+    /// uncertain.
+    pub fn oracle_price(
+        &self,
+        oracle: &PriceFeed,
+        fallback_oracle: Option<&PriceFeed>,
+        time: i64,
+    ) -> Result<Price> {
+        oracle
+            .checked_price(self.max_staleness, self.max_confidence, time)
+            .or_else(|err| match fallback_oracle {
+                Some(fallback_oracle) => {
+                    fallback_oracle.checked_price(self.max_staleness, self.max_confidence, time)
+                }
+                None => Err(err),
+            })
+    }
+
+    /// Multiplier offered for a new bet of `amount` on `side`, skewed against the side
+    /// that already carries more exposure.
+    pub fn quote_multiplier(&self, side: bool, amount: u64) -> u64 {
+        if self.odds_liquidity == 0 {
+            return self.payout_multiplier;
+        }
+
+        let (same, other) = if side {
+            (self.high_exposure, self.low_exposure)
+        } else {
+            (self.low_exposure, self.high_exposure)
+        };
+        let liquidity = self.odds_liquidity as u128;
+        let multiplier = self.payout_multiplier as u128 * (other as u128 + liquidity)
+            / (same as u128 + amount as u128 + liquidity);
+
+        multiplier.clamp(100, self.max_multiplier as u128) as u64
+    }
+
+    /// Index of the bucket the move from `entry_price` to `actual_price` falls into.
+    pub fn bucket_for(&self, entry_price: Price, actual_price: Price) -> u8 {
+        let price_move = price_move(entry_price, actual_price);
+        let bounds = &self.bucket_bounds[..self.bucket_count.saturating_sub(1) as usize];
+
+        bounds
+            .iter()
+            .take_while(|bound| price_move >= **bound as i128)
+            .count() as u8
+    }
+
+    fn twap_sample(&self, index: usize) -> TwapSample {
+        self.twap_samples[index % TWAP_SAMPLES]
+    }
+
+    pub fn last_twap_sample(&self) -> Option<TwapSample> {
+        let count = self.twap_sample_count as usize;
+
+        (count > 0).then(|| self.twap_sample(count - 1))
+    }
+
+    /// Time-weighted average of the sampled prices, each weighted by the time until
+    /// the next sample (or the market end for the latest one), at the finest exponent
+    /// among them.
+    pub fn twap(&self) -> Option<Price> {
+        let count = self.twap_sample_count as usize;
+        let first = count.saturating_sub(TWAP_SAMPLES);
+        if count == 0 {
+            return None;
+        }
+
+        let expo = (first..count)
+            .map(|i| self.twap_sample(i).price.expo)
+            .min()?;
+
+        let mut price_sum = 0u128;
+        let mut weighted_sum = 0u128;
+        let mut total_weight = 0u128;
+        for i in first..count {
+            let sample = self.twap_sample(i);
+            let until = if i + 1 < count {
+                self.twap_sample(i + 1).timestamp
+            } else {
+                self.end
+            };
+            let weight = (until - sample.timestamp).max(0) as u128;
+
+            let price = sample.price.scaled_to(expo);
+            price_sum += price;
+            weighted_sum = weighted_sum.saturating_add(price.saturating_mul(weight));
+            total_weight += weight;
+        }
+
+        // All samples taken at the very end, fall back to a plain average
+        let mantissa = weighted_sum
+            .checked_div(total_weight)
+            .unwrap_or(price_sum / (count - first) as u128);
+
+        Some(Price {
+            mantissa: mantissa.min(u64::MAX as u128) as u64,
+            expo,
+        })
+    }
+
+    /// Whether `prediction` wins if the market settled at `price`.
+    pub fn is_winning(&self, prediction: &Prediction, price: Price) -> bool {
+        match self.kind {
+            MarketKind::HigherLower => {
+                (price > prediction.market_price && prediction.prediction)
+                    || (price < prediction.market_price && !prediction.prediction)
+            }
+            MarketKind::PercentageMove => {
+                self.moved_beyond_threshold(prediction.market_price, price) == prediction.prediction
+            }
+            MarketKind::Range => {
+                self.bucket_for(prediction.market_price, price) == prediction.bucket
+            }
+        }
+    }
+
+    /// Value of closing `prediction` early at `price`: its payout times its chance of
+    /// winning, less `cash_out_spread`. The chance starts at the odds of the multiplier and
+    /// moves with how far `price` is into (or out of) the winning range, a move counting
+    /// for more as the end nears. At the end, the position is worth its payout or nothing.
+    pub fn cash_out_value(&self, prediction: &Prediction, price: Price, now: i64) -> u64 {
+        let basis_points = crate::constants::BASIS_POINTS as i128;
+        let payout = prediction.payout() as i128;
+        if payout == 0 {
+            return 0;
+        }
+
+        let duration = (self.end - self.start).max(1) as i128;
+        let remaining = (self.end - now).clamp(0, self.end - self.start) as i128;
+        let chance = if remaining == 0 {
+            if self.is_winning(prediction, price) {
+                basis_points
+            } else {
+                0
+            }
+        } else {
+            // A move of the whole price over the whole market is worth certainty
+            let width = (basis_points * remaining / duration).max(1);
+            let odds = prediction.amount as i128 * basis_points / payout;
+            let distance = self.winning_distance(prediction, price);
+
+            odds.saturating_add(distance.saturating_mul(basis_points) / width)
+                .clamp(0, basis_points)
+        };
+        let fair_value = payout * chance / basis_points;
+        let spread = basis_points - self.cash_out_spread as i128;
+
+        (fair_value * spread / basis_points) as u64
+    }
+
+    /// How far in basis points the move from the entry price of `prediction` to `price` is
+    /// inside its winning range, negative when outside.
+    fn winning_distance(&self, prediction: &Prediction, price: Price) -> i128 {
+        let price_move = price_move(prediction.market_price, price);
+
+        match self.kind {
+            MarketKind::HigherLower if prediction.prediction => price_move,
+            MarketKind::HigherLower => -price_move,
+            MarketKind::PercentageMove => {
+                let beyond = price_move.abs() - self.move_threshold as i128;
+                if prediction.prediction {
+                    beyond
+                } else {
+                    -beyond
+                }
+            }
+            MarketKind::Range => {
+                let bounds = &self.bucket_bounds[..self.bucket_count.saturating_sub(1) as usize];
+                let bucket = prediction.bucket as usize;
+                let above_lower = bucket
+                    .checked_sub(1)
+                    .and_then(|lower| bounds.get(lower))
+                    .map_or(i128::MAX, |lower| price_move - *lower as i128);
+                let below_upper = bounds
+                    .get(bucket)
+                    .map_or(i128::MAX, |upper| *upper as i128 - price_move);
+
+                above_lower.min(below_upper)
+            }
+        }
+    }
+
+    /// Whether the price moved at least `move_threshold` away from `entry_price`.
+    pub fn moved_beyond_threshold(&self, entry_price: Price, actual_price: Price) -> bool {
+        price_move(entry_price, actual_price).unsigned_abs() >= self.move_threshold as u128
+    }
+}
+
+/// Price move from `entry_price` to `actual_price` in basis points.
+fn price_move(entry_price: Price, actual_price: Price) -> i128 {
+    let expo = entry_price.expo.min(actual_price.expo);
+    let entry_price = entry_price.scaled_to(expo) as i128;
+    let actual_price = actual_price.scaled_to(expo) as i128;
+
+    (actual_price - entry_price).saturating_mul(crate::constants::BASIS_POINTS as i128)
+        / entry_price
+}
+
+/// Price as `mantissa * 10^expo`, the convention of oracles.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, InitSpace)]
+pub struct Price {
+    pub mantissa: u64,
+    pub expo: i32,
+}
+
+impl Price {
+    /// Mantissa of the price expressed with `expo`, truncated when `expo` is coarser.
+    pub fn scaled_to(&self, expo: i32) -> u128 {
+        let shift = self.expo.abs_diff(expo);
+
+        if self.expo >= expo {
+            (self.mantissa as u128).saturating_mul(10u128.saturating_pow(shift))
+        } else {
+            self.mantissa as u128 / 10u128.saturating_pow(shift)
+        }
+    }
+}
+
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    /// Compares the prices at the finer of both exponents.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let expo = self.expo.min(other.expo);
+
+        self.scaled_to(expo).cmp(&other.scaled_to(expo))
+    }
+}
+
+impl std::fmt::Display for Price {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.expo >= 0 {
+            return write!(f, "{}", self.scaled_to(0));
+        }
+
+        let decimals = self.expo.unsigned_abs() as usize;
+        let digits = format!("{:0>width$}", self.mantissa, width = decimals + 1);
+        let (int, frac) = digits.split_at(digits.len() - decimals);
+
+        write!(f, "{int}.{frac}")
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct MarketMetadata {
+    #[max_len(MAX_TITLE_LEN)]
+    pub title: String,
+    #[max_len(MAX_SYMBOL_LEN)]
+    pub symbol: String,
+    pub decimals: u8, // Decimals to display prices of the token with
+    #[max_len(MAX_IMAGE_URI_LEN)]
+    pub image_uri: String,
+}
+
+impl MarketMetadata {
+    pub fn validate(&self) -> Result<()> {
+        if self.title.len() > MAX_TITLE_LEN
+            || self.symbol.len() > MAX_SYMBOL_LEN
+            || self.image_uri.len() > MAX_IMAGE_URI_LEN
+        {
+            return Err(ZoneErrorCode::MetadataTooLong.into());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct TwapSample {
+    pub price: Price,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum MarketKind {
+    HigherLower,    // Binary bet on price ending higher or lower
+    Range,          // Bet on which bucket the price move ends in
+    PercentageMove, // Bet on price moving at least a threshold in either direction
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Prediction {
+    pub version: u8,
+    pub user: Pubkey,
+    pub market: Pubkey,
+    pub prediction: bool, // True for higher (or moved), False for lower (or stayed)
+    pub bucket: u8,       // Chosen bucket for range markets
+    pub market_price: Price, // Market price at prediction time
+    pub amount: u64,      // Amount wagered
+    pub payout_multiplier: u64, // Multiplier locked in at prediction time
+    pub status: PredictionStatus,
+    pub vault: Pubkey,      // Vault the amount was wagered against
+    pub reserved: [u8; 64], // Room for new fields
+}
+
+impl Prediction {
+    /// Amount paid out if the prediction wins.
+    pub fn payout(&self) -> u64 {
+        (self.amount * self.payout_multiplier) / 100
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PredictionStatus {
+    Open,
+    Settled,
+    CashedOut,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub version: u8,
+    pub authority: Pubkey, // Publisher allowed to update the price
+    pub token_account: Pubkey,
+    pub price: u64,
+    pub conf: u64,          // Confidence interval around the price
+    pub expo: i32,          // Exponent of price and conf
+    pub publish_time: i64,  // Unix timestamp of the last update
+    pub reserved: [u8; 64], // Room for new fields
+}
+
+impl PriceFeed {
+    /// Price of the feed, unless it was published more than `max_staleness` seconds
+    /// before or after `time` or its confidence interval is wider than `max_confidence`
+    /// basis points of it.
+    pub fn checked_price(
+        &self,
+        max_staleness: i64,
+        max_confidence: u64,
+        time: i64,
+    ) -> Result<Price> {
+        if self.price == 0 {
+            return Err(ZoneErrorCode::InvalidPrice.into());
+        }
+
+        if (time - self.publish_time).abs() > max_staleness {
+            return Err(ZoneErrorCode::StalePrice.into());
+        }
+
+        if self.conf as u128 * crate::constants::BASIS_POINTS as u128
+            > max_confidence as u128 * self.price as u128
+        {
+            return Err(ZoneErrorCode::PriceTooUncertain.into());
+        }
+
+        Ok(Price {
+            mantissa: self.price,
+            expo: self.expo,
+        })
+    }
+}
+
+/// Layout of `Market` before accounts were versioned, only read by `migrate_market`.
+#[derive(AnchorDeserialize)]
+pub struct LegacyMarket {
+    pub authority: Pubkey,
+    pub token_account: Pubkey,
+    pub started: bool,
+    pub start: i64,
+    pub end: i64,
+    pub payout_multiplier: u64,
+}
+
+impl LegacyMarket {
+    /// Reads `account` if it still has the legacy layout.
+    pub fn load(account: &AccountInfo) -> Result<Self> {
+        let data = account.try_borrow_data()?;
+
+        // Legacy markets were allocated their in-memory size
+        if data.len() != 8 + std::mem::size_of::<Self>() {
+            return Err(ZoneErrorCode::AlreadyMigrated.into());
+        }
+
+        if data[..8] != Market::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+impl From<LegacyMarket> for Market {
+    fn from(legacy: LegacyMarket) -> Self {
+        Self {
+            version: crate::constants::ACCOUNT_VERSION,
+            authority: legacy.authority,
+            token_account: legacy.token_account,
+            started: legacy.started,
+            start: legacy.start,
+            end: legacy.end,
+            payout_multiplier: legacy.payout_multiplier,
+            kind: MarketKind::HigherLower,
+            bucket_count: 0,
+            bucket_bounds: [0; MAX_BUCKETS],
+            bucket_multipliers: [0; MAX_BUCKETS],
+            move_threshold: 0,
+            odds_liquidity: 0,
+            max_multiplier: 0,
+            high_exposure: 0,
+            low_exposure: 0,
+            liability: 0,
+            oracle: Pubkey::default(),
+            fallback_oracle: Pubkey::default(),
+            max_staleness: 0,
+            max_confidence: 0,
+            cash_out_spread: 0,
+            twap_window: 0,
+            twap_samples: [TwapSample::default(); TWAP_SAMPLES],
+            twap_sample_count: 0,
+            resolved: false,
+            final_price: Price::default(),
+            resolved_at: 0,
+            dispute_period: 0,
+            resolver: Pubkey::default(),
+            finalized: false,
+            metadata: MarketMetadata::default(),
+            open_predictions: 0,
+            reserved: [0; 56],
+        }
+    }
+}
+
+/// Layout of `Prediction` before accounts were versioned, only read by `migrate_prediction`.
+#[derive(AnchorDeserialize)]
+pub struct LegacyPrediction {
+    pub user: Pubkey,
+    pub market: Pubkey,
+    pub prediction: bool,
+    pub market_price: u64,
+    pub amount: u64,
+}
+
+impl LegacyPrediction {
+    /// Reads `account` if it still has the legacy layout.
+    pub fn load(account: &AccountInfo) -> Result<Self> {
+        let data = account.try_borrow_data()?;
+
+        // Legacy predictions were allocated their in-memory size
+        if data.len() != 8 + std::mem::size_of::<Self>() {
+            return Err(ZoneErrorCode::AlreadyMigrated.into());
+        }
+
+        if data[..8] != Prediction::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+
+    /// Open prediction with its market price read at `market_price_expo`, paying out
+    /// `payout_multiplier` from `vault`.
+    pub fn migrate(
+        self,
+        market_price_expo: i32,
+        payout_multiplier: u64,
+        vault: Pubkey,
+    ) -> Prediction {
+        Prediction {
+            version: crate::constants::ACCOUNT_VERSION,
+            user: self.user,
+            market: self.market,
+            prediction: self.prediction,
+            bucket: 0,
+            market_price: Price {
+                mantissa: self.market_price,
+                expo: market_price_expo,
+            },
+            amount: self.amount,
+            payout_multiplier,
+            status: PredictionStatus::Open,
+            vault,
+            reserved: [0; 64],
+        }
+    }
+}
+
+#[event]
+pub struct ResolutionProposed {
+    pub market: Pubkey,
+    pub final_price: Price,
+    pub dispute_end: i64,
+}
+
+#[event]
+pub struct ResolutionDisputed {
+    pub market: Pubkey,
+    pub resolver: Pubkey,
+    pub proposed_price: Price,
+    pub corrected_price: Price,
+}
+
+#[event]
+pub struct ResolutionFinalized {
+    pub market: Pubkey,
+    pub final_price: Price,
+}
+
+#[error_code]
+#[derive(num_enum::TryFromPrimitive)]
+pub enum ZoneErrorCode {
+    #[msg("Market has already started")]
+    AlreadyStarted,
+
+    #[msg("Market has not started yet")]
+    NotStarted,
+
+    #[msg("Market has not finished yet")]
+    NotFinished,
+
+    #[msg("Not enough SOL")]
     NotEnoughSol,
+
+    #[msg("Invalid bucket configuration")]
+    InvalidBucketConfig,
+
+    #[msg("Invalid bucket for this market")]
+    InvalidBucket,
+
+    #[msg("Invalid price")]
+    InvalidPrice,
+
+    #[msg("Move threshold must be greater than zero")]
+    InvalidMoveThreshold,
+
+    #[msg("Max multiplier must be at least 100")]
+    InvalidMaxMultiplier,
+
+    #[msg("Offered multiplier is below the minimum accepted")]
+    SlippageExceeded,
+
+    #[msg("Market has already finished")]
+    AlreadyFinished,
+
+    #[msg("Prediction is already closed")]
+    PredictionClosed,
+
+    #[msg("Oracle does not match the market")]
+    InvalidOracle,
+
+    #[msg("Cash out spread must be at most 10000 basis points")]
+    InvalidCashOutSpread,
+
+    #[msg("TWAP window must not be negative")]
+    InvalidTwapWindow,
+
+    #[msg("Not within the TWAP window of the market")]
+    OutsideTwapWindow,
+
+    #[msg("Price was sampled too recently")]
+    SampleTooSoon,
+
+    #[msg("Market has already been resolved")]
+    AlreadyResolved,
+
+    #[msg("Market has not been resolved yet")]
+    NotResolved,
+
+    #[msg("Max staleness and max confidence must be greater than zero")]
+    InvalidOracleLimits,
+
+    #[msg("Oracle price is stale")]
+    StalePrice,
+
+    #[msg("Oracle price confidence interval is too wide")]
+    PriceTooUncertain,
+
+    #[msg("Dispute period must not be negative")]
+    InvalidDisputePeriod,
+
+    #[msg("Signer is not allowed to dispute the resolution")]
+    NotResolver,
+
+    #[msg("Dispute window has already closed")]
+    DisputeWindowClosed,
+
+    #[msg("Dispute window has not closed yet")]
+    DisputeWindowOpen,
+
+    #[msg("Resolution has already been finalized")]
+    AlreadyFinalized,
+
+    #[msg("Resolution has not been finalized yet")]
+    NotFinalized,
+
+    #[msg("Withdrawal delay must not be negative")]
+    InvalidWithdrawalDelay,
+
+    #[msg("Vault has no assets left to back LP shares")]
+    VaultInsolvent,
+
+    #[msg("Not enough LP shares")]
+    InsufficientShares,
+
+    #[msg("No withdrawal has been requested")]
+    NoPendingWithdrawal,
+
+    #[msg("Withdrawal is delayed while predictions are open")]
+    WithdrawalLocked,
+
+    #[msg("Prediction exceeds the liability limit of the market")]
+    MarketLiabilityExceeded,
+
+    #[msg("Prediction exceeds the liability limit of the vault")]
+    TotalLiabilityExceeded,
+
+    #[msg("Prediction exceeds the imbalance limit of the market")]
+    ImbalanceExceeded,
+
+    #[msg("Market metadata is too long")]
+    MetadataTooLong,
+
+    #[msg("Price exponent is out of range")]
+    InvalidExponent,
+
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+
+    #[msg("Market still has open predictions")]
+    OpenPredictions,
+
+    #[msg("Prediction lost, settle it as a losing prediction")]
+    PredictionLost,
+
+    #[msg("Withdrawal request has expired, request it again")]
+    WithdrawalExpired,
+
+    #[msg("Prediction won, settle it as a winning prediction")]
+    PredictionWon,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Market read from a zeroed allocation, a higher/lower market with nothing set.
+    fn market() -> Market {
+        let data = [0; 8 + Market::INIT_SPACE];
+
+        Market::try_deserialize_unchecked(&mut &data[..]).unwrap()
+    }
+
+    fn prediction(prediction: bool, market_price: Price) -> Prediction {
+        let data = [0; 8 + Prediction::INIT_SPACE];
+        let mut account = Prediction::try_deserialize_unchecked(&mut &data[..]).unwrap();
+        account.prediction = prediction;
+        account.market_price = market_price;
+
+        account
+    }
+
+    fn price(mantissa: u64, expo: i32) -> Price {
+        Price { mantissa, expo }
+    }
+
+    #[test]
+    fn test_bucket_for() {
+        let mut market = market();
+        market.kind = MarketKind::Range;
+        market.bucket_count = 4;
+        market.bucket_bounds[..3].copy_from_slice(&[-1_000, 0, 1_000]);
+        let entry_price = price(100_000, -5);
+
+        assert_eq!(market.bucket_for(entry_price, price(85_000, -5)), 0);
+        assert_eq!(market.bucket_for(entry_price, price(95_000, -5)), 1);
+        // Bounds belong to the bucket above them
+        assert_eq!(market.bucket_for(entry_price, price(100_000, -5)), 2);
+        assert_eq!(market.bucket_for(entry_price, price(105_000, -5)), 2);
+        assert_eq!(market.bucket_for(entry_price, price(110_000, -5)), 3);
+        assert_eq!(market.bucket_for(entry_price, price(2, 0)), 3);
+    }
+
+    #[test]
+    fn test_percentage_move_settlement() {
+        let mut market = market();
+        market.kind = MarketKind::PercentageMove;
+        market.move_threshold = 500;
+        let moved = prediction(true, price(100_000, -5));
+        let stayed = prediction(false, price(100_000, -5));
+
+        // Moves of exactly the threshold count, either way
+        for settle_price in [price(105_000, -5), price(95_000, -5), price(2, 0)] {
+            assert!(market.is_winning(&moved, settle_price));
+            assert!(!market.is_winning(&stayed, settle_price));
+        }
+
+        for settle_price in [price(104_999, -5), price(95_001, -5), price(1, 0)] {
+            assert!(!market.is_winning(&moved, settle_price));
+            assert!(market.is_winning(&stayed, settle_price));
+        }
+    }
+
+    #[test]
+    fn test_quote_multiplier() {
+        let mut market = market();
+        market.payout_multiplier = 200;
+
+        // Fixed odds without liquidity to damp them
+        assert_eq!(market.quote_multiplier(true, 1_000), 200);
+
+        market.odds_liquidity = 1_000;
+        market.max_multiplier = 300;
+        assert_eq!(market.quote_multiplier(true, 100), 181);
+
+        // Clamped to at least 1x when piling onto the crowded side
+        market.high_exposure = 100_000;
+        assert_eq!(market.quote_multiplier(true, 1_000), 100);
+
+        // And to at most `max_multiplier` on the other side
+        assert_eq!(market.quote_multiplier(false, 1_000), 300);
+    }
+
+    #[test]
+    fn test_price_scaled_to() {
+        assert_eq!(price(2, -5).scaled_to(-5), 2);
+        assert_eq!(price(2, -5).scaled_to(-6), 20);
+        // Truncated to a coarser exponent
+        assert_eq!(price(15, -1).scaled_to(0), 1);
+        assert_eq!(price(2, -5).scaled_to(-4), 0);
+        assert_eq!(price(3, 2).scaled_to(0), 300);
+    }
+
+    #[test]
+    fn test_price_ord() {
+        assert!(price(2, -5) > price(19, -6));
+        assert!(price(2, -5) < price(21, -6));
+        assert_eq!(price(2, -5), price(20, -6));
+        assert_eq!(price(1, 0), price(1_000_000_000_000_000_000, -18));
+        assert_eq!(price(3, 2).cmp(&price(300, 0)), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn test_price_display() {
+        assert_eq!(price(2, -5).to_string(), "0.00002");
+        assert_eq!(price(150, -2).to_string(), "1.50");
+        assert_eq!(price(12, 0).to_string(), "12");
+        assert_eq!(price(12, 2).to_string(), "1200");
+    }
+
+    #[test]
+    fn test_settlement_across_exponents() {
+        let market = market();
+        let higher = prediction(true, price(100_000, -5));
+        let lower = prediction(false, price(100_000, -5));
+
+        assert!(market.is_winning(&higher, price(11, -1)));
+        assert!(!market.is_winning(&lower, price(11, -1)));
+
+        assert!(!market.is_winning(&higher, price(999_999, -6)));
+        assert!(market.is_winning(&lower, price(999_999, -6)));
+
+        // Neither side wins when the price didn't move
+        assert!(!market.is_winning(&higher, price(1, 0)));
+        assert!(!market.is_winning(&lower, price(1, 0)));
+    }
+
+    #[test]
+    fn test_cash_out_value() {
+        let mut market = market();
+        market.start = 0;
+        market.end = 1000;
+        let mut higher = prediction(true, price(100_000, -5));
+        higher.amount = 100;
+        higher.payout_multiplier = 200;
+        let winning = price(110_000, -5);
+        let losing = price(90_000, -5);
+
+        // At the start, worth the stake unless the price moved
+        assert_eq!(market.cash_out_value(&higher, price(100_000, -5), 0), 100);
+        assert_eq!(market.cash_out_value(&higher, winning, 0), 120);
+        assert_eq!(market.cash_out_value(&higher, losing, 0), 80);
+
+        // Halfway, the same move counts twice as much
+        assert_eq!(market.cash_out_value(&higher, winning, 500), 140);
+        assert_eq!(market.cash_out_value(&higher, losing, 500), 60);
+
+        // At the end, the payout or nothing
+        assert_eq!(market.cash_out_value(&higher, winning, 1000), 200);
+        assert_eq!(market.cash_out_value(&higher, losing, 1000), 0);
+
+        // Far out of the money before the end
+        assert_eq!(market.cash_out_value(&higher, price(40_000, -5), 0), 0);
+
+        market.cash_out_spread = 200;
+        assert_eq!(market.cash_out_value(&higher, price(100_000, -5), 0), 98);
+    }
+
+    #[test]
+    fn test_cash_out_value_by_kind() {
+        let mut market = market();
+        market.start = 0;
+        market.end = 1000;
+        let mut moved = prediction(true, price(100_000, -5));
+        moved.amount = 100;
+        moved.payout_multiplier = 200;
+        let mut stayed = moved.clone();
+        stayed.prediction = false;
+
+        // 10% move, 5% beyond the threshold
+        market.kind = MarketKind::PercentageMove;
+        market.move_threshold = 500;
+        assert_eq!(market.cash_out_value(&moved, price(110_000, -5), 0), 110);
+        assert_eq!(market.cash_out_value(&stayed, price(110_000, -5), 0), 90);
+
+        // 5% move, 5% below the upper bound of the middle bucket
+        market.kind = MarketKind::Range;
+        market.bucket_count = 3;
+        market.bucket_bounds[..2].copy_from_slice(&[-1000, 1000]);
+        moved.bucket = 1;
+        assert_eq!(market.cash_out_value(&moved, price(105_000, -5), 0), 110);
+        moved.bucket = 2;
+        assert_eq!(market.cash_out_value(&moved, price(105_000, -5), 0), 90);
+    }
 }
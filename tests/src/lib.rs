@@ -9,12 +9,17 @@ use anchor_client::{
     Client, ClientError, Cluster, Program,
 };
 use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
 use chrono::{DateTime, Utc};
-use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use client::output::ProgramErrorInfo;
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, system_instruction};
 
 #[allow(unused_imports)]
 mod test;
 
+#[cfg(test)]
+mod space;
+
 pub const PROGRAM_ID: &str = "7UyLYeoNwWeh3LgMUnWFKPc1Ebwr8Afzsz8hVjgavoRa";
 
 pub struct TestSetup {
@@ -54,6 +59,32 @@ impl TestSetup {
         vault_pda
     }
 
+    pub fn get_lp_mint_pda(&self, vault_num: u8) -> Pubkey {
+        let (lp_mint_pda, _bump) = Pubkey::find_program_address(
+            &[b"lp_mint", self.get_vault_pda(vault_num).as_ref()],
+            &self.program_id,
+        );
+
+        lp_mint_pda
+    }
+
+    pub fn get_lp_account(&self, vault_num: u8) -> Pubkey {
+        get_associated_token_address(&self.payer.pubkey(), &self.get_lp_mint_pda(vault_num))
+    }
+
+    pub fn get_lp_position_pda(&self, vault_num: u8) -> Pubkey {
+        let (lp_position_pda, _bump) = Pubkey::find_program_address(
+            &[
+                b"lp_position",
+                self.get_vault_pda(vault_num).as_ref(),
+                self.payer.pubkey().as_ref(),
+            ],
+            &self.program_id,
+        );
+
+        lp_position_pda
+    }
+
     pub fn get_market_pda(&self, token_account: Pubkey) -> Pubkey {
         let (market_pda, _bump) =
             Pubkey::find_program_address(&[b"market", token_account.as_ref()], &self.program_id);
@@ -62,13 +93,18 @@ impl TestSetup {
     }
 
     pub fn get_prediction_pda(&self, token_account: Pubkey) -> Pubkey {
+        self.get_user_prediction_pda(token_account, self.payer.pubkey())
+    }
+
+    pub fn get_user_prediction_pda(&self, token_account: Pubkey, user: Pubkey) -> Pubkey {
         let market_pda = self.get_market_pda(token_account);
 
         let (prediction_pda, _bump) = Pubkey::find_program_address(
             &[
                 b"prediction",
                 market_pda.as_ref(),
-                self.payer.pubkey().as_ref(),
+                user.as_ref(),
+                &self.get_market_start(market_pda).to_le_bytes(),
             ],
             &self.program_id,
         );
@@ -76,6 +112,59 @@ impl TestSetup {
         prediction_pda
     }
 
+    pub fn get_vault_exposure_pda(&self, vault_num: u8, token_account: Pubkey) -> Pubkey {
+        self.get_market_vault_exposure_pda(vault_num, self.get_market_pda(token_account))
+    }
+
+    pub fn get_market_vault_exposure_pda(&self, vault_num: u8, market: Pubkey) -> Pubkey {
+        let (vault_exposure_pda, _bump) = Pubkey::find_program_address(
+            &[
+                b"vault_exposure",
+                self.get_vault_pda(vault_num).as_ref(),
+                market.as_ref(),
+                &self.get_market_start(market).to_le_bytes(),
+            ],
+            &self.program_id,
+        );
+
+        vault_exposure_pda
+    }
+
+    /// Start of the current run of the market, part of the seeds of its predictions.
+    pub fn get_market_start(&self, market: Pubkey) -> i64 {
+        self.program
+            .account::<zone::Market>(market)
+            .map_or(0, |market| market.start)
+    }
+
+    pub fn get_price_feed_pda(&self, token_account: Pubkey) -> Pubkey {
+        let (price_feed_pda, _bump) = Pubkey::find_program_address(
+            &[
+                b"price_feed",
+                token_account.as_ref(),
+                self.payer.pubkey().as_ref(),
+            ],
+            &self.program_id,
+        );
+
+        price_feed_pda
+    }
+
+    pub fn balance(&self, account: Pubkey) -> u64 {
+        self.program.rpc().get_balance(&account).unwrap()
+    }
+
+    pub fn fund(&self, account: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .instruction(system_instruction::transfer(
+                &self.payer.pubkey(),
+                &account,
+                LAMPORTS_PER_SOL,
+            ))
+            .send()
+    }
+
     pub fn initialize(&self, vault_num: u8) -> Result<Signature, ClientError> {
         self.program
             .request()
@@ -91,6 +180,147 @@ impl TestSetup {
             .send()
     }
 
+    pub fn initialize_as(
+        &self,
+        authority: &Keypair,
+        vault_num: u8,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::Initialize {
+                vault: self.get_vault_pda(vault_num),
+                authority: authority.pubkey(),
+                system_program: system_program::ID,
+            })
+            .args(zone::instruction::Initialize {
+                amount: LAMPORTS_PER_SOL / 2,
+                vault_num,
+            })
+            .signer(authority)
+            .send()
+    }
+
+    pub fn initialize_lp_mint(&self, vault_num: u8) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::InitializeLpMint {
+                vault: self.get_vault_pda(vault_num),
+                lp_mint: self.get_lp_mint_pda(vault_num),
+                authority_lp_account: self.get_lp_account(vault_num),
+                authority: self.payer.pubkey(),
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            })
+            .args(zone::instruction::InitializeLpMint { vault_num })
+            .send()
+    }
+
+    pub fn set_withdrawal_delay(
+        &self,
+        vault_num: u8,
+        withdrawal_delay: i64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetWithdrawalDelay {
+                vault: self.get_vault_pda(vault_num),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetWithdrawalDelay {
+                vault_num,
+                withdrawal_delay,
+            })
+            .send()
+    }
+
+    pub fn set_risk_limits(
+        &self,
+        vault_num: u8,
+        max_market_liability: u64,
+        max_total_liability: u64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetRiskLimits {
+                vault: self.get_vault_pda(vault_num),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetRiskLimits {
+                vault_num,
+                max_market_liability,
+                max_total_liability,
+                max_imbalance: 0,
+            })
+            .send()
+    }
+
+    pub fn set_max_imbalance(
+        &self,
+        vault_num: u8,
+        max_imbalance: u64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetRiskLimits {
+                vault: self.get_vault_pda(vault_num),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetRiskLimits {
+                vault_num,
+                max_market_liability: 0,
+                max_total_liability: 0,
+                max_imbalance,
+            })
+            .send()
+    }
+
+    pub fn deposit(&self, vault_num: u8, amount: u64) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::Deposit {
+                vault: self.get_vault_pda(vault_num),
+                lp_mint: self.get_lp_mint_pda(vault_num),
+                depositor_lp_account: self.get_lp_account(vault_num),
+                depositor: self.payer.pubkey(),
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            })
+            .args(zone::instruction::Deposit { vault_num, amount })
+            .send()
+    }
+
+    pub fn request_withdrawal(&self, vault_num: u8, shares: u64) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::RequestWithdrawal {
+                vault: self.get_vault_pda(vault_num),
+                lp_position: self.get_lp_position_pda(vault_num),
+                lp_mint: self.get_lp_mint_pda(vault_num),
+                owner_lp_account: self.get_lp_account(vault_num),
+                owner: self.payer.pubkey(),
+                system_program: system_program::ID,
+            })
+            .args(zone::instruction::RequestWithdrawal { vault_num, shares })
+            .send()
+    }
+
+    pub fn withdraw(&self, vault_num: u8) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::Withdraw {
+                vault: self.get_vault_pda(vault_num),
+                lp_position: self.get_lp_position_pda(vault_num),
+                lp_mint: self.get_lp_mint_pda(vault_num),
+                owner_lp_account: self.get_lp_account(vault_num),
+                owner: self.payer.pubkey(),
+                token_program: anchor_spl::token::ID,
+            })
+            .args(zone::instruction::Withdraw { vault_num })
+            .send()
+    }
+
     pub fn initialize_market(&self, token_account: Pubkey) -> Result<Signature, ClientError> {
         self.program
             .request()
@@ -102,6 +332,194 @@ impl TestSetup {
             .args(zone::instruction::InitializeMarket {
                 token_account,
                 payout_multiplier: 200,
+                metadata: None,
+            })
+            .send()
+    }
+
+    pub fn update_market_metadata(
+        &self,
+        token_account: Pubkey,
+        title: &str,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::UpdateMarketMetadata {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::UpdateMarketMetadata {
+                metadata: zone::MarketMetadata {
+                    title: title.to_string(),
+                    symbol: "TEST".to_string(),
+                    decimals: 6,
+                    image_uri: String::new(),
+                },
+            })
+            .send()
+    }
+
+    pub fn set_buckets(
+        &self,
+        token_account: Pubkey,
+        bounds: Vec<i64>,
+        multipliers: Vec<u64>,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetBuckets {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetBuckets {
+                bounds,
+                multipliers,
+            })
+            .send()
+    }
+
+    pub fn set_move_threshold(
+        &self,
+        token_account: Pubkey,
+        move_threshold: u64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetMoveThreshold {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetMoveThreshold { move_threshold })
+            .send()
+    }
+
+    pub fn set_dynamic_odds(
+        &self,
+        token_account: Pubkey,
+        odds_liquidity: u64,
+        max_multiplier: u64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetDynamicOdds {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetDynamicOdds {
+                odds_liquidity,
+                max_multiplier,
+            })
+            .send()
+    }
+
+    pub fn initialize_price_feed(&self, token_account: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::InitializePriceFeed {
+                price_feed: self.get_price_feed_pda(token_account),
+                authority: self.payer.pubkey(),
+                system_program: system_program::ID,
+            })
+            .args(zone::instruction::InitializePriceFeed { token_account })
+            .send()
+    }
+
+    pub fn update_price_feed(
+        &self,
+        token_account: Pubkey,
+        price: u64,
+        expo: i32,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::UpdatePriceFeed {
+                price_feed: self.get_price_feed_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::UpdatePriceFeed {
+                price,
+                conf: 0,
+                expo,
+            })
+            .send()
+    }
+
+    /// Market whose oracle is a price feed of this wallet at `price`, with an exponent of -5.
+    pub fn initialize_market_with_oracle(
+        &self,
+        token_account: Pubkey,
+        price: u64,
+    ) -> Result<Signature, ClientError> {
+        self.initialize_market(token_account)?;
+        self.initialize_price_feed(token_account)?;
+        self.update_price_feed(token_account, price, -5)?;
+        self.set_oracle(token_account, 60)
+    }
+
+    pub fn set_oracle(
+        &self,
+        token_account: Pubkey,
+        max_staleness: i64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetOracle {
+                market: self.get_market_pda(token_account),
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetOracle {
+                max_staleness,
+                max_confidence: 100,
+            })
+            .send()
+    }
+
+    pub fn set_cash_out_spread(
+        &self,
+        token_account: Pubkey,
+        cash_out_spread: u64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetCashOutSpread {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetCashOutSpread { cash_out_spread })
+            .send()
+    }
+
+    pub fn set_twap_window(
+        &self,
+        token_account: Pubkey,
+        twap_window: i64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetTwapWindow {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetTwapWindow { twap_window })
+            .send()
+    }
+
+    pub fn set_dispute_period(
+        &self,
+        token_account: Pubkey,
+        dispute_period: i64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetDisputePeriod {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
+            })
+            .args(zone::instruction::SetDisputePeriod {
+                dispute_period,
+                resolver: self.payer.pubkey(),
             })
             .send()
     }
@@ -115,6 +533,8 @@ impl TestSetup {
             .request()
             .accounts(zone::accounts::StartMarket {
                 market: self.get_market_pda(token_account),
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
             })
             .args(zone::instruction::StartMarket {
                 end: end.timestamp(),
@@ -126,21 +546,119 @@ impl TestSetup {
         &self,
         vault_num: u8,
         token_account: Pubkey,
+    ) -> Result<Signature, ClientError> {
+        self.bet(vault_num, token_account, true, None, 100, 0)
+    }
+
+    pub fn create_prediction_as(
+        &self,
+        user: &Keypair,
+        vault_num: u8,
+        token_account: Pubkey,
     ) -> Result<Signature, ClientError> {
         self.program
             .request()
             .accounts(zone::accounts::CreatePrediction {
-                prediction: self.get_prediction_pda(token_account),
-                user: self.payer.pubkey(),
+                prediction: self.get_user_prediction_pda(token_account, user.pubkey()),
+                vault_exposure: self.get_vault_exposure_pda(vault_num, token_account),
+                user: user.pubkey(),
                 market: self.get_market_pda(token_account),
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
                 system_program: system_program::ID,
                 vault: self.get_vault_pda(vault_num),
             })
             .args(zone::instruction::CreatePrediction {
                 prediction: true,
+                bucket: None,
                 amount: 100,
-                current_price: 100_000,
+                min_multiplier: 0,
             })
+            .signer(user)
+            .send()
+    }
+
+    pub fn bet(
+        &self,
+        vault_num: u8,
+        token_account: Pubkey,
+        prediction: bool,
+        bucket: Option<u8>,
+        amount: u64,
+        min_multiplier: u64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::CreatePrediction {
+                prediction: self.get_prediction_pda(token_account),
+                vault_exposure: self.get_vault_exposure_pda(vault_num, token_account),
+                user: self.payer.pubkey(),
+                market: self.get_market_pda(token_account),
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
+                system_program: system_program::ID,
+                vault: self.get_vault_pda(vault_num),
+            })
+            .args(zone::instruction::CreatePrediction {
+                prediction,
+                bucket,
+                amount,
+                min_multiplier,
+            })
+            .send()
+    }
+
+    pub fn sample_price(&self, token_account: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SamplePrice {
+                market: self.get_market_pda(token_account),
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
+            })
+            .args(zone::instruction::SamplePrice {})
+            .send()
+    }
+
+    pub fn resolve_market(&self, token_account: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::ResolveMarket {
+                market: self.get_market_pda(token_account),
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
+            })
+            .args(zone::instruction::ResolveMarket {})
+            .send()
+    }
+
+    pub fn dispute_resolution(
+        &self,
+        token_account: Pubkey,
+        corrected_price: u64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::DisputeResolution {
+                market: self.get_market_pda(token_account),
+                resolver: self.payer.pubkey(),
+            })
+            .args(zone::instruction::DisputeResolution {
+                corrected_price: zone::Price {
+                    mantissa: corrected_price,
+                    expo: -5,
+                },
+            })
+            .send()
+    }
+
+    pub fn finalize_resolution(&self, token_account: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::FinalizeResolution {
+                market: self.get_market_pda(token_account),
+            })
+            .args(zone::instruction::FinalizeResolution {})
             .send()
     }
 
@@ -148,21 +666,206 @@ impl TestSetup {
         &self,
         vault_num: u8,
         token_account: Pubkey,
+    ) -> Result<Signature, ClientError> {
+        self.settle_prediction_as(&self.payer, vault_num, token_account)
+    }
+
+    pub fn settle_prediction_as(
+        &self,
+        user: &Keypair,
+        vault_num: u8,
+        token_account: Pubkey,
     ) -> Result<Signature, ClientError> {
         self.program
             .request()
             .accounts(zone::accounts::SettlePrediction {
+                prediction: self.get_user_prediction_pda(token_account, user.pubkey()),
+                vault_exposure: self.get_vault_exposure_pda(vault_num, token_account),
+                user: user.pubkey(),
+                market: self.get_market_pda(token_account),
+                vault: self.get_vault_pda(vault_num),
+            })
+            .args(zone::instruction::SettlePrediction {})
+            .signer(user)
+            .send()
+    }
+
+    pub fn settle_winning_prediction(
+        &self,
+        vault_num: u8,
+        token_account: Pubkey,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SettleWinningPrediction {
+                vault: self.get_vault_pda(vault_num),
                 prediction: self.get_prediction_pda(token_account),
+                vault_exposure: self.get_vault_exposure_pda(vault_num, token_account),
                 user: self.payer.pubkey(),
                 market: self.get_market_pda(token_account),
-                system_program: system_program::ID,
+            })
+            .args(zone::instruction::SettleWinningPrediction {})
+            .send()
+    }
+
+    pub fn settle_losing_prediction(
+        &self,
+        vault_num: u8,
+        token_account: Pubkey,
+    ) -> Result<Signature, ClientError> {
+        self.settle_losing_prediction_of(self.payer.pubkey(), vault_num, token_account)
+    }
+
+    pub fn settle_losing_prediction_of(
+        &self,
+        user: Pubkey,
+        vault_num: u8,
+        token_account: Pubkey,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SettleLosingPrediction {
                 vault: self.get_vault_pda(vault_num),
+                prediction: self.get_user_prediction_pda(token_account, user),
+                vault_exposure: self.get_vault_exposure_pda(vault_num, token_account),
+                market: self.get_market_pda(token_account),
+            })
+            .args(zone::instruction::SettleLosingPrediction {})
+            .send()
+    }
+
+    pub fn cash_out(&self, vault_num: u8, token_account: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::CashOut {
+                prediction: self.get_prediction_pda(token_account),
+                vault_exposure: self.get_vault_exposure_pda(vault_num, token_account),
+                user: self.payer.pubkey(),
+                market: self.get_market_pda(token_account),
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
+                vault: self.get_vault_pda(vault_num),
+            })
+            .args(zone::instruction::CashOut {})
+            .send()
+    }
+
+    pub fn close_market(&self, token_account: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::CloseMarket {
+                market: self.get_market_pda(token_account),
+                authority: self.payer.pubkey(),
             })
-            .args(zone::instruction::SettlePrediction {
-                actual_price: 20_000,
+            .args(zone::instruction::CloseMarket {})
+            .send()
+    }
+
+    pub fn migrate_market(&self, market: Pubkey) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::MigrateMarket {
+                market,
+                payer: self.payer.pubkey(),
+                system_program: system_program::ID,
+            })
+            .args(zone::instruction::MigrateMarket {})
+            .send()
+    }
+
+    pub fn migrate_prediction(
+        &self,
+        authority: &Keypair,
+        vault_num: u8,
+        market: Pubkey,
+        prediction: Pubkey,
+        market_price_expo: i32,
+    ) -> Result<Signature, ClientError> {
+        let vault = self.get_vault_pda(vault_num);
+        let vault_exposure = self.get_market_vault_exposure_pda(vault_num, market);
+
+        self.program
+            .request()
+            .accounts(zone::accounts::MigratePrediction {
+                prediction,
+                market,
+                vault,
+                vault_exposure,
+                authority: authority.pubkey(),
+                payer: self.payer.pubkey(),
+                system_program: system_program::ID,
             })
+            .args(zone::instruction::MigratePrediction { market_price_expo })
+            .signer(authority)
             .send()
     }
+
+    pub fn set_migrated_oracle(
+        &self,
+        authority: &Keypair,
+        market: Pubkey,
+        token_account: Pubkey,
+        max_staleness: i64,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::SetOracle {
+                market,
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
+                authority: authority.pubkey(),
+            })
+            .args(zone::instruction::SetOracle {
+                max_staleness,
+                max_confidence: 100,
+            })
+            .signer(authority)
+            .send()
+    }
+
+    pub fn resolve_migrated_market(
+        &self,
+        market: Pubkey,
+        token_account: Pubkey,
+    ) -> Result<Signature, ClientError> {
+        self.program
+            .request()
+            .accounts(zone::accounts::ResolveMarket {
+                market,
+                oracle: self.get_price_feed_pda(token_account),
+                fallback_oracle: None,
+            })
+            .args(zone::instruction::ResolveMarket {})
+            .send()
+    }
+
+    pub fn settle_migrated_prediction(
+        &self,
+        vault_num: u8,
+        market: Pubkey,
+        prediction: Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let vault = self.get_vault_pda(vault_num);
+        let vault_exposure = self.get_market_vault_exposure_pda(vault_num, market);
+
+        self.program
+            .request()
+            .accounts(zone::accounts::SettleLosingPrediction {
+                vault,
+                prediction,
+                vault_exposure,
+                market,
+            })
+            .args(zone::instruction::SettleLosingPrediction {})
+            .send()
+    }
+}
+
+/// Code of the program error a transaction failed with, None if it went through.
+pub fn error_code(result: &Result<Signature, ClientError>) -> Option<u32> {
+    let err = result.as_ref().err()?;
+
+    ProgramErrorInfo::from_client_error(err).map(|info| info.code)
 }
 
 impl Default for TestSetup {
@@ -0,0 +1,79 @@
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator, Space};
+use zone::{
+    constants::{MAX_IMAGE_URI_LEN, MAX_SYMBOL_LEN, MAX_TITLE_LEN},
+    LiquidityPosition, Market, Prediction, PriceFeed, Vault, VaultExposure,
+};
+
+/// Account read from a zeroed allocation of `8 + T::INIT_SPACE` bytes.
+fn zeroed<T: AccountDeserialize + Discriminator + Space>() -> T {
+    let mut data = vec![0; 8 + T::INIT_SPACE];
+    data[..8].copy_from_slice(&T::DISCRIMINATOR);
+
+    T::try_deserialize(&mut &data[..]).unwrap()
+}
+
+/// Size of the account once serialized, discriminator included.
+fn serialized_len<T: AccountSerialize>(account: &T) -> usize {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+
+    data.len()
+}
+
+#[test]
+fn test_vault_space() {
+    let vault: Vault = zeroed();
+
+    assert_eq!(serialized_len(&vault), 8 + Vault::INIT_SPACE);
+}
+
+#[test]
+fn test_vault_exposure_space() {
+    let vault_exposure: VaultExposure = zeroed();
+
+    assert_eq!(
+        serialized_len(&vault_exposure),
+        8 + VaultExposure::INIT_SPACE
+    );
+}
+
+#[test]
+fn test_liquidity_position_space() {
+    let lp_position: LiquidityPosition = zeroed();
+
+    assert_eq!(
+        serialized_len(&lp_position),
+        8 + LiquidityPosition::INIT_SPACE
+    );
+}
+
+#[test]
+fn test_market_space() {
+    let mut market: Market = zeroed();
+    market.metadata.title = "a".repeat(MAX_TITLE_LEN);
+    market.metadata.symbol = "a".repeat(MAX_SYMBOL_LEN);
+    market.metadata.image_uri = "a".repeat(MAX_IMAGE_URI_LEN);
+
+    assert_eq!(serialized_len(&market), 8 + Market::INIT_SPACE);
+}
+
+#[test]
+fn test_prediction_space() {
+    let prediction: Prediction = zeroed();
+
+    assert_eq!(serialized_len(&prediction), 8 + Prediction::INIT_SPACE);
+}
+
+#[test]
+fn test_price_feed_space() {
+    let price_feed: PriceFeed = zeroed();
+
+    assert_eq!(serialized_len(&price_feed), 8 + PriceFeed::INIT_SPACE);
+}
+
+#[test]
+fn test_legacy_space() {
+    // Sizes the original program allocated, which the legacy layouts are recognized by
+    assert_eq!(8 + std::mem::size_of::<zone::LegacyMarket>(), 104);
+    assert_eq!(8 + std::mem::size_of::<zone::LegacyPrediction>(), 96);
+}
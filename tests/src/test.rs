@@ -9,12 +9,19 @@ use anchor_client::{
     },
     Client, Cluster,
 };
-use anchor_lang::system_program;
+use anchor_lang::{error::ErrorCode, system_program};
 use chrono::Utc;
 use sequential_test::sequential;
-use solana_program::native_token::LAMPORTS_PER_SOL;
+use solana_program::{native_token::LAMPORTS_PER_SOL, system_instruction::SystemError};
+use zone::ZoneErrorCode;
 
-use crate::TestSetup;
+use crate::{error_code, TestSetup};
+
+#[allow(dead_code)]
+const LEGACY_MARKET_ADDRESS: &str = "2ictJRGHVWVK5LUg4VmpNPda8deNeE4u5pB3bi1D9HJY";
+
+#[allow(dead_code)]
+const LEGACY_PREDICTION_ADDRESS: &str = "zdXUizmcnv3iSenErYtt9MUmyHQCLH2rnUwV5v8rsYb";
 
 #[allow(dead_code)]
 const WIF_TOKEN_ADDRESS: &str = "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm";
@@ -36,9 +43,17 @@ fn test_initialize() {
     let success_res = setup.initialize(0);
     assert!(success_res.is_ok());
 
+    let vault: zone::Vault = setup.program.account(setup.get_vault_pda(0)).unwrap();
+    assert_eq!(vault.authority, setup.payer.pubkey());
+    assert_eq!(vault.vault_num, 0);
+    assert_eq!(vault.deposits, 100 * LAMPORTS_PER_SOL);
+
     // Fail pattern (Same vault)
     let fail_res = setup.initialize(0);
-    assert!(fail_res.is_err());
+    assert_eq!(
+        error_code(&fail_res),
+        Some(SystemError::AccountAlreadyInUse as u32)
+    );
 }
 
 #[test]
@@ -55,7 +70,147 @@ fn test_initialize_market() {
 
     // Fail pattern (Already initialized)
     let fail_res = setup.initialize_market(token_account);
-    assert!(fail_res.is_err());
+    assert_eq!(
+        error_code(&fail_res),
+        Some(SystemError::AccountAlreadyInUse as u32)
+    );
+}
+
+#[test]
+fn test_set_buckets() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+
+    // Fail pattern (Bounds not ascending)
+    let fail_res = setup.set_buckets(token_account, vec![0, -1_000], vec![300, 200, 300]);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidBucketConfig.into())
+    );
+
+    // Fail pattern (Bounds and multipliers mismatch)
+    let fail_res = setup.set_buckets(token_account, vec![0], vec![300, 200, 300]);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidBucketConfig.into())
+    );
+
+    // Success pattern
+    let success_res = setup.set_buckets(
+        token_account,
+        vec![-1_000, 0, 1_000],
+        vec![400, 200, 200, 400],
+    );
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already started the market)
+    let _ = setup.start_market(token_account, end);
+    let fail_res = setup.set_buckets(token_account, vec![0], vec![200, 200]);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyStarted.into())
+    );
+}
+
+#[test]
+fn test_range_prediction() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 11;
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.set_buckets(
+        token_account,
+        vec![-1_000, 0, 1_000],
+        vec![400, 200, 200, 400],
+    );
+    let _ = setup.start_market(token_account, end);
+
+    // Fail pattern (No bucket)
+    let fail_res = setup.bet(vault_num, token_account, true, None, 100, 0);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidBucket.into())
+    );
+
+    // Fail pattern (Bucket out of range)
+    let fail_res = setup.bet(vault_num, token_account, true, Some(4), 100, 0);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidBucket.into())
+    );
+
+    // Success pattern
+    let success_res = setup.bet(vault_num, token_account, true, Some(3), 100, 0);
+    assert!(success_res.is_ok());
+
+    let prediction: zone::Prediction = setup
+        .program
+        .account(setup.get_prediction_pda(token_account))
+        .unwrap();
+    assert_eq!(prediction.bucket, 3);
+    assert_eq!(prediction.payout_multiplier, 400);
+}
+
+#[test]
+fn test_set_move_threshold() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+
+    // Fail pattern (Zero threshold)
+    let fail_res = setup.set_move_threshold(token_account, 0);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidMoveThreshold.into())
+    );
+
+    // Success pattern
+    let success_res = setup.set_move_threshold(token_account, 500);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already started the market)
+    let _ = setup.start_market(token_account, end);
+    let fail_res = setup.set_move_threshold(token_account, 1_000);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyStarted.into())
+    );
+}
+
+#[test]
+fn test_set_dynamic_odds() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+
+    // Fail pattern (Max multiplier below 1x)
+    let fail_res = setup.set_dynamic_odds(token_account, 10 * LAMPORTS_PER_SOL, 50);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidMaxMultiplier.into())
+    );
+
+    // Success pattern
+    let success_res = setup.set_dynamic_odds(token_account, 10 * LAMPORTS_PER_SOL, 500);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already started the market)
+    let _ = setup.start_market(token_account, end);
+    let fail_res = setup.set_dynamic_odds(token_account, 10 * LAMPORTS_PER_SOL, 500);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyStarted.into())
+    );
 }
 
 #[test]
@@ -66,7 +221,7 @@ fn test_start_market() {
     let end = Utc::now() + chrono::Duration::days(1);
 
     let _ = setup.initialize(2);
-    let _ = setup.initialize_market(token_account);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
 
     // Success pattern
     let success_res = setup.start_market(token_account, end);
@@ -74,7 +229,10 @@ fn test_start_market() {
 
     // Fail pattern (Already started the market)
     let fail_res = setup.start_market(token_account, end);
-    assert!(fail_res.is_err());
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyStarted.into())
+    );
 }
 
 #[test]
@@ -86,7 +244,15 @@ fn test_create_prediction() {
     let end = Utc::now() + chrono::Duration::days(1);
 
     let _ = setup.initialize(vault_num);
-    let _ = setup.initialize_market(token_account);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+
+    // Fail pattern (Not started)
+    let fail_res = setup.create_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NotStarted.into())
+    );
+
     let _ = setup.start_market(token_account, end);
 
     // Success pattern
@@ -95,5 +261,1179 @@ fn test_create_prediction() {
 
     // Fail pattern (Already created)
     let fail_res = setup.create_prediction(vault_num, token_account);
-    assert!(fail_res.is_err());
+    assert_eq!(
+        error_code(&fail_res),
+        Some(SystemError::AccountAlreadyInUse as u32)
+    );
+
+    // Fail pattern (Already ended)
+    let token_account = Pubkey::new_unique();
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, Utc::now() - chrono::Duration::minutes(1));
+    let fail_res = setup.create_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyFinished.into())
+    );
+}
+
+#[test]
+fn test_slippage() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 12;
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.set_dynamic_odds(token_account, 10 * LAMPORTS_PER_SOL, 500);
+    let _ = setup.start_market(token_account, end);
+
+    // Fail pattern (Offered 1.99x, below the minimum)
+    let fail_res = setup.bet(vault_num, token_account, true, None, 100, 200);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::SlippageExceeded.into())
+    );
+
+    // Success pattern
+    let success_res = setup.bet(vault_num, token_account, true, None, 100, 199);
+    assert!(success_res.is_ok());
+
+    let prediction: zone::Prediction = setup
+        .program
+        .account(setup.get_prediction_pda(token_account))
+        .unwrap();
+    assert_eq!(prediction.payout_multiplier, 199);
+}
+
+#[test]
+fn test_set_oracle() {
+    let token_account = Pubkey::new_unique();
+    let other_token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+
+    let _ = setup.initialize_market(token_account);
+    let _ = setup.initialize_price_feed(token_account);
+    let _ = setup.initialize_price_feed(other_token_account);
+
+    // Fail pattern (Price feed of another token)
+    let fail_res = setup
+        .program
+        .request()
+        .accounts(zone::accounts::SetOracle {
+            market: setup.get_market_pda(token_account),
+            oracle: setup.get_price_feed_pda(other_token_account),
+            fallback_oracle: None,
+            authority: setup.payer.pubkey(),
+        })
+        .args(zone::instruction::SetOracle {
+            max_staleness: 60,
+            max_confidence: 100,
+        })
+        .send();
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidOracle.into())
+    );
+
+    // Fail pattern (Zero max staleness)
+    let fail_res = setup.set_oracle(token_account, 0);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidOracleLimits.into())
+    );
+
+    // Success pattern
+    let success_res = setup.set_oracle(token_account, 60);
+    assert!(success_res.is_ok());
+}
+
+#[test]
+fn test_stale_oracle_price() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.set_oracle(token_account, 1);
+
+    sleep(std::time::Duration::from_secs(3));
+
+    // Fail pattern (Stale oracle price)
+    let fail_res = setup.start_market(token_account, end);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::StalePrice.into())
+    );
+
+    // Success pattern
+    let _ = setup.update_price_feed(token_account, 100_000, -5);
+    let success_res = setup.start_market(token_account, end);
+    assert!(success_res.is_ok());
+}
+
+#[test]
+fn test_set_cash_out_spread() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+
+    // Fail pattern (Spread above 100%)
+    let fail_res = setup.set_cash_out_spread(token_account, 10_001);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidCashOutSpread.into())
+    );
+
+    // Success pattern
+    let success_res = setup.set_cash_out_spread(token_account, 200);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already started the market)
+    let _ = setup.start_market(token_account, end);
+    let fail_res = setup.set_cash_out_spread(token_account, 0);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyStarted.into())
+    );
+}
+
+#[test]
+fn test_cash_out() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 4;
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 120_000);
+    let _ = setup.set_cash_out_spread(token_account, 200);
+    let _ = setup.start_market(token_account, end);
+    let _ = setup.create_prediction(vault_num, token_account);
+
+    let vault_balance = setup.balance(setup.get_vault_pda(vault_num));
+
+    // Success pattern
+    let success_res = setup.cash_out(vault_num, token_account);
+    assert!(success_res.is_ok());
+
+    // The price didn't move, so the stake of 100 lamports less the 2% spread
+    assert_eq!(
+        vault_balance - setup.balance(setup.get_vault_pda(vault_num)),
+        98
+    );
+
+    // Fail pattern (Already cashed out)
+    let fail_res = setup.cash_out(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::PredictionClosed.into())
+    );
+}
+
+#[test]
+fn test_sample_price() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+
+    // Fail pattern (No TWAP window)
+    let _ = setup.start_market(token_account, end);
+    let fail_res = setup.sample_price(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::OutsideTwapWindow.into())
+    );
+
+    // TWAP window covering the whole market
+    let token_account = Pubkey::new_unique();
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.set_twap_window(token_account, 2 * 24 * 60 * 60);
+    let _ = setup.start_market(token_account, end);
+
+    // Success pattern
+    let success_res = setup.sample_price(token_account);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Sampled too recently)
+    let fail_res = setup.sample_price(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::SampleTooSoon.into())
+    );
+}
+
+#[test]
+fn test_resolve_market() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() - chrono::Duration::seconds(10);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+
+    // Fail pattern (Not started)
+    let fail_res = setup.resolve_market(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NotFinished.into())
+    );
+
+    let _ = setup.start_market(token_account, end);
+
+    // Success pattern
+    let success_res = setup.resolve_market(token_account);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already resolved)
+    let fail_res = setup.resolve_market(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyResolved.into())
+    );
+
+    // Fail pattern (No price published around the end)
+    let token_account = Pubkey::new_unique();
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, Utc::now() - chrono::Duration::hours(1));
+    let fail_res = setup.resolve_market(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::StalePrice.into())
+    );
+}
+
+#[test]
+fn test_resolve_market_without_twap_samples() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() - chrono::Duration::seconds(10);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.set_twap_window(token_account, 60);
+    let _ = setup.start_market(token_account, end);
+
+    // Success pattern (Spot price without samples)
+    let success_res = setup.resolve_market(token_account);
+    assert!(success_res.is_ok());
+
+    let market: zone::Market = setup
+        .program
+        .account(setup.get_market_pda(token_account))
+        .unwrap();
+    assert_eq!(market.final_price.mantissa, 100_000);
+    assert_eq!(market.final_price.expo, -5);
+}
+
+#[test]
+fn test_dispute_resolution() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() - chrono::Duration::seconds(10);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.set_dispute_period(token_account, 60 * 60);
+    let _ = setup.start_market(token_account, end);
+
+    // Fail pattern (Not resolved yet)
+    let fail_res = setup.dispute_resolution(token_account, 90_000);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NotResolved.into())
+    );
+
+    let _ = setup.resolve_market(token_account);
+
+    // Fail pattern (Not the resolver)
+    let other = Keypair::new();
+    let fail_res = setup
+        .program
+        .request()
+        .accounts(zone::accounts::DisputeResolution {
+            market: setup.get_market_pda(token_account),
+            resolver: other.pubkey(),
+        })
+        .args(zone::instruction::DisputeResolution {
+            corrected_price: zone::Price {
+                mantissa: 90_000,
+                expo: -5,
+            },
+        })
+        .signer(&other)
+        .send();
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NotResolver.into())
+    );
+
+    // Success pattern
+    let success_res = setup.dispute_resolution(token_account, 90_000);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Dispute window still open)
+    let fail_res = setup.finalize_resolution(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::DisputeWindowOpen.into())
+    );
+}
+
+#[test]
+fn test_finalize_resolution() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let end = Utc::now() - chrono::Duration::seconds(10);
+
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.set_dispute_period(token_account, 1);
+    let _ = setup.start_market(token_account, end);
+    let _ = setup.resolve_market(token_account);
+
+    sleep(std::time::Duration::from_secs(3));
+
+    // Fail pattern (Dispute window closed)
+    let fail_res = setup.dispute_resolution(token_account, 90_000);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::DisputeWindowClosed.into())
+    );
+
+    // Success pattern
+    let success_res = setup.finalize_resolution(token_account);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already finalized)
+    let fail_res = setup.finalize_resolution(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyFinalized.into())
+    );
+}
+
+#[test]
+fn test_initialize_lp_mint() {
+    let setup = TestSetup::new();
+    let vault_num = 5;
+
+    let _ = setup.initialize(vault_num);
+
+    // Success pattern
+    let success_res = setup.initialize_lp_mint(vault_num);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already initialized)
+    let fail_res = setup.initialize_lp_mint(vault_num);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(SystemError::AccountAlreadyInUse as u32)
+    );
+}
+
+#[test]
+fn test_deposit_and_withdraw() {
+    let setup = TestSetup::new();
+    let vault_num = 6;
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_lp_mint(vault_num);
+
+    // Success pattern
+    let success_res = setup.deposit(vault_num, LAMPORTS_PER_SOL);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Nothing requested)
+    let fail_res = setup.withdraw(vault_num);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NoPendingWithdrawal.into())
+    );
+
+    // Fail pattern (More shares than owned)
+    let fail_res = setup.request_withdrawal(vault_num, 1_000 * LAMPORTS_PER_SOL);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InsufficientShares.into())
+    );
+
+    let success_res = setup.request_withdrawal(vault_num, LAMPORTS_PER_SOL);
+    assert!(success_res.is_ok());
+
+    // Success pattern (No open predictions, no delay)
+    let success_res = setup.withdraw(vault_num);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already withdrawn)
+    let fail_res = setup.withdraw(vault_num);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NoPendingWithdrawal.into())
+    );
+}
+
+#[test]
+fn test_set_risk_limits() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 7;
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, end);
+
+    // Success pattern
+    let success_res = setup.set_risk_limits(vault_num, 0, 100);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Payout above the liability limit)
+    let fail_res = setup.create_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::TotalLiabilityExceeded.into())
+    );
+
+    let _ = setup.set_risk_limits(vault_num, 0, 0);
+    let success_res = setup.create_prediction(vault_num, token_account);
+    assert!(success_res.is_ok());
+}
+
+#[test]
+fn test_market_liability_limit() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 14;
+    let other_vault_num = 15;
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize(other_vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, end);
+    let _ = setup.create_prediction(other_vault_num, token_account);
+
+    // Fail pattern (Payout above the liability limit in the market)
+    let _ = setup.set_risk_limits(vault_num, 100, 0);
+    let user = Keypair::new();
+    let _ = setup.fund(user.pubkey());
+    let fail_res = setup.create_prediction_as(&user, vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::MarketLiabilityExceeded.into())
+    );
+
+    // Success pattern (Liability of other vaults left out)
+    let _ = setup.set_risk_limits(vault_num, 200, 0);
+    let success_res = setup.create_prediction_as(&user, vault_num, token_account);
+    assert!(success_res.is_ok());
+
+    let vault_exposure: zone::VaultExposure = setup
+        .program
+        .account(setup.get_vault_exposure_pda(vault_num, token_account))
+        .unwrap();
+    assert_eq!(vault_exposure.liability, 200);
+}
+
+#[test]
+fn test_imbalance_limit() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 19;
+    let other_vault_num = 20;
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize(other_vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, end);
+    let _ = setup.create_prediction(other_vault_num, token_account);
+    let _ = setup.set_max_imbalance(vault_num, 150);
+
+    // Success pattern (Bets backed by other vaults left out)
+    let user = Keypair::new();
+    let _ = setup.fund(user.pubkey());
+    let success_res = setup.create_prediction_as(&user, vault_num, token_account);
+    assert!(success_res.is_ok());
+
+    let vault_exposure: zone::VaultExposure = setup
+        .program
+        .account(setup.get_vault_exposure_pda(vault_num, token_account))
+        .unwrap();
+    assert_eq!(vault_exposure.high_exposure, 100);
+    assert_eq!(vault_exposure.imbalance(), 100);
+
+    // Fail pattern (Imbalance of the vault above the limit)
+    let other_user = Keypair::new();
+    let _ = setup.fund(other_user.pubkey());
+    let fail_res = setup.create_prediction_as(&other_user, vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::ImbalanceExceeded.into())
+    );
+}
+
+#[test]
+fn test_update_market_metadata() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+
+    let _ = setup.initialize_market(token_account);
+
+    // Success pattern
+    let success_res = setup.update_market_metadata(token_account, "Test coin higher or lower");
+    assert!(success_res.is_ok());
+
+    let market: zone::Market = setup
+        .program
+        .account(setup.get_market_pda(token_account))
+        .unwrap();
+    assert_eq!(market.metadata.title, "Test coin higher or lower");
+
+    // Fail pattern (Title too long)
+    let fail_res = setup.update_market_metadata(token_account, &"a".repeat(65));
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::MetadataTooLong.into())
+    );
+}
+
+#[test]
+fn test_price_exponent() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+
+    let _ = setup.initialize_price_feed(token_account);
+
+    // Success pattern
+    let success_res = setup.update_price_feed(token_account, 2, -5);
+    assert!(success_res.is_ok());
+
+    let price_feed: zone::PriceFeed = setup
+        .program
+        .account(setup.get_price_feed_pda(token_account))
+        .unwrap();
+    let price = price_feed
+        .checked_price(60, 100, price_feed.publish_time)
+        .unwrap();
+    assert_eq!(price.to_string(), "0.00002");
+    assert!(
+        price
+            > zone::Price {
+                mantissa: 19,
+                expo: -6
+            }
+    );
+
+    // Fail pattern (Exponent out of range)
+    let fail_res = setup.update_price_feed(token_account, 2, -19);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidExponent.into())
+    );
+
+    let fail_res = setup.update_price_feed(token_account, 2, i32::MIN);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidExponent.into())
+    );
+}
+
+#[test]
+fn test_migrate() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 8;
+    let end = Utc::now() + chrono::Duration::days(1);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, end);
+    let _ = setup.create_prediction(vault_num, token_account);
+
+    let market: zone::Market = setup
+        .program
+        .account(setup.get_market_pda(token_account))
+        .unwrap();
+    assert_eq!(market.version, zone::constants::ACCOUNT_VERSION);
+
+    // Fail pattern (Already on the current layout)
+    let fail_res = setup.migrate_market(setup.get_market_pda(token_account));
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyMigrated.into())
+    );
+
+    let fail_res = setup.migrate_prediction(
+        &setup.payer,
+        vault_num,
+        setup.get_market_pda(token_account),
+        setup.get_prediction_pda(token_account),
+        -5,
+    );
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyMigrated.into())
+    );
+}
+
+#[test]
+fn test_migrate_legacy_accounts() {
+    // Loaded into the validator from tests/fixtures, see Anchor.toml
+    let market_pda = Pubkey::from_str(LEGACY_MARKET_ADDRESS).unwrap();
+    let prediction_pda = Pubkey::from_str(LEGACY_PREDICTION_ADDRESS).unwrap();
+    let authority = read_keypair_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fixtures/legacy_authority.json"
+    ))
+    .unwrap();
+    let setup = TestSetup::new();
+    let vault_num = 16;
+
+    let _ = setup.fund(authority.pubkey());
+    let _ = setup.initialize_as(&authority, vault_num);
+
+    // Fail pattern (Market not migrated yet)
+    let fail_res = setup.migrate_prediction(&authority, vault_num, market_pda, prediction_pda, -5);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ErrorCode::AccountDidNotDeserialize.into())
+    );
+
+    let res = setup.migrate_market(market_pda);
+    assert!(res.is_ok());
+
+    let market: zone::Market = setup.program.account(market_pda).unwrap();
+    assert_eq!(market.version, zone::constants::ACCOUNT_VERSION);
+    assert_eq!(market.authority, authority.pubkey());
+    assert!(market.started);
+    assert_eq!(market.end, 1_700_086_400);
+    assert_eq!(market.payout_multiplier, 200);
+    assert!(market.kind == zone::MarketKind::HigherLower);
+
+    // Fail pattern (Exponent out of range)
+    let fail_res = setup.migrate_prediction(&authority, vault_num, market_pda, prediction_pda, -19);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidExponent.into())
+    );
+
+    // Fail pattern (Not the market authority)
+    let fail_res =
+        setup.migrate_prediction(&setup.payer, vault_num, market_pda, prediction_pda, -5);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ErrorCode::ConstraintHasOne.into())
+    );
+
+    let res = setup.migrate_prediction(&authority, vault_num, market_pda, prediction_pda, -5);
+    assert!(res.is_ok());
+
+    let prediction: zone::Prediction = setup.program.account(prediction_pda).unwrap();
+    assert_eq!(prediction.version, zone::constants::ACCOUNT_VERSION);
+    assert_eq!(prediction.market, market_pda);
+    assert!(prediction.prediction);
+    assert_eq!(prediction.market_price.mantissa, 12_345_678);
+    assert_eq!(prediction.market_price.expo, -5);
+    assert_eq!(prediction.amount, LAMPORTS_PER_SOL);
+    assert_eq!(prediction.payout_multiplier, 200);
+    assert!(prediction.status == zone::PredictionStatus::Open);
+    assert_eq!(prediction.vault, setup.get_vault_pda(vault_num));
+
+    let market: zone::Market = setup.program.account(market_pda).unwrap();
+    assert_eq!(market.liability, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(market.open_predictions, 1);
+
+    let vault: zone::Vault = setup
+        .program
+        .account(setup.get_vault_pda(vault_num))
+        .unwrap();
+    assert_eq!(vault.liabilities, 2 * LAMPORTS_PER_SOL);
+
+    // Fail pattern (Already migrated)
+    let fail_res = setup.migrate_prediction(&authority, vault_num, market_pda, prediction_pda, -5);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyMigrated.into())
+    );
+
+    // The legacy market ended long ago, the staleness has to reach back to its end
+    let token_account = market.token_account;
+    let max_staleness = Utc::now().timestamp() - market.end + 3600;
+    let _ = setup.initialize_price_feed(token_account);
+    let _ = setup.update_price_feed(token_account, 10_000_000, -5);
+
+    // Fail pattern (No oracle yet)
+    let fail_res = setup.resolve_migrated_market(market_pda, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::InvalidOracle.into())
+    );
+
+    // Fail pattern (Not the market authority)
+    let fail_res = setup
+        .program
+        .request()
+        .accounts(zone::accounts::SetOracle {
+            market: market_pda,
+            oracle: setup.get_price_feed_pda(token_account),
+            fallback_oracle: None,
+            authority: setup.payer.pubkey(),
+        })
+        .args(zone::instruction::SetOracle {
+            max_staleness,
+            max_confidence: 100,
+        })
+        .send();
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ErrorCode::ConstraintHasOne.into())
+    );
+
+    let res = setup.set_migrated_oracle(&authority, market_pda, token_account, max_staleness);
+    assert!(res.is_ok());
+
+    // Fail pattern (Oracle already set on a started market)
+    let fail_res = setup.set_migrated_oracle(&authority, market_pda, token_account, max_staleness);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::AlreadyStarted.into())
+    );
+
+    let res = setup.resolve_migrated_market(market_pda, token_account);
+    assert!(res.is_ok());
+
+    let market: zone::Market = setup.program.account(market_pda).unwrap();
+    assert!(market.resolved);
+    assert_eq!(market.final_price.mantissa, 10_000_000);
+
+    // The prediction was HIGH at 123.45678 and the market closed at 100
+    let res = setup.settle_migrated_prediction(vault_num, market_pda, prediction_pda);
+    assert!(res.is_ok());
+
+    let prediction: zone::Prediction = setup.program.account(prediction_pda).unwrap();
+    assert!(prediction.status == zone::PredictionStatus::Settled);
+
+    let market: zone::Market = setup.program.account(market_pda).unwrap();
+    assert_eq!(market.liability, 0);
+    assert_eq!(market.open_predictions, 0);
+
+    let vault: zone::Vault = setup
+        .program
+        .account(setup.get_vault_pda(vault_num))
+        .unwrap();
+    assert_eq!(vault.liabilities, 0);
+    assert_eq!(vault.realized_pnl, LAMPORTS_PER_SOL as i64);
+}
+
+#[test]
+fn test_settle_losing_prediction_paths() {
+    let token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 21;
+    let end = Utc::now() + chrono::Duration::seconds(5);
+    let user = Keypair::new();
+    let other_user = Keypair::new();
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, end);
+    for user in [&user, &other_user] {
+        let _ = setup.fund(user.pubkey());
+        let _ = setup.create_prediction_as(user, vault_num, token_account);
+    }
+
+    sleep(std::time::Duration::from_secs(6));
+
+    let _ = setup.update_price_feed(token_account, 90_000, -5);
+    let _ = setup.resolve_market(token_account);
+
+    let vault_pda = setup.get_vault_pda(vault_num);
+    let vault_balance = setup.balance(vault_pda);
+    let vault_before: zone::Vault = setup.program.account(vault_pda).unwrap();
+
+    // Settled by its user
+    let user_balance = setup.balance(user.pubkey());
+    let success_res = setup.settle_prediction_as(&user, vault_num, token_account);
+    assert!(success_res.is_ok());
+    assert_eq!(setup.balance(user.pubkey()), user_balance);
+
+    let vault: zone::Vault = setup.program.account(vault_pda).unwrap();
+    assert_eq!(vault.realized_pnl, vault_before.realized_pnl + 100);
+    assert_eq!(vault.liabilities, vault_before.liabilities - 200);
+
+    // Settled by anyone else, with the same outcome
+    let other_user_balance = setup.balance(other_user.pubkey());
+    let success_res =
+        setup.settle_losing_prediction_of(other_user.pubkey(), vault_num, token_account);
+    assert!(success_res.is_ok());
+    assert_eq!(setup.balance(other_user.pubkey()), other_user_balance);
+
+    let vault: zone::Vault = setup.program.account(vault_pda).unwrap();
+    assert_eq!(vault.realized_pnl, vault_before.realized_pnl + 200);
+    assert_eq!(vault.liabilities, vault_before.liabilities - 400);
+
+    // Both stakes were already in the vault
+    assert_eq!(setup.balance(vault_pda), vault_balance);
+}
+
+#[test]
+fn test_close_market() {
+    let token_account = Pubkey::new_unique();
+    let other_token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 9;
+    let end = Utc::now() + chrono::Duration::seconds(5);
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    let _ = setup.start_market(token_account, end);
+    let _ = setup.create_prediction(vault_num, token_account);
+
+    // Fail pattern (Not finished)
+    let fail_res = setup.close_market(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NotFinished.into())
+    );
+
+    sleep(std::time::Duration::from_secs(6));
+
+    let _ = setup.update_price_feed(token_account, 110_000, -5);
+    let _ = setup.resolve_market(token_account);
+
+    // Fail pattern (Predictions still open)
+    let fail_res = setup.close_market(token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::OpenPredictions.into())
+    );
+
+    let _ = setup.settle_prediction(vault_num, token_account);
+    let prediction_pda = setup.get_prediction_pda(token_account);
+    let vault_exposure_pda = setup.get_vault_exposure_pda(vault_num, token_account);
+
+    // Success pattern (All predictions settled)
+    let success_res = setup.close_market(token_account);
+    assert!(success_res.is_ok());
+
+    let market_res: Result<zone::Market, _> =
+        setup.program.account(setup.get_market_pda(token_account));
+    assert!(market_res.is_err());
+
+    // Success pattern (Same user bets again once the market is reopened)
+    let _ = setup.initialize_market(token_account);
+    let _ = setup.update_price_feed(token_account, 100_000, -5);
+    let _ = setup.set_oracle(token_account, 60);
+    let _ = setup.start_market(token_account, Utc::now() + chrono::Duration::days(1));
+    let success_res = setup.create_prediction(vault_num, token_account);
+    assert!(success_res.is_ok());
+
+    assert_ne!(setup.get_prediction_pda(token_account), prediction_pda);
+    assert_ne!(
+        setup.get_vault_exposure_pda(vault_num, token_account),
+        vault_exposure_pda
+    );
+    let prediction: zone::Prediction = setup
+        .program
+        .account(setup.get_prediction_pda(token_account))
+        .unwrap();
+    assert!(prediction.status == zone::PredictionStatus::Open);
+    let vault_exposure: zone::VaultExposure = setup
+        .program
+        .account(setup.get_vault_exposure_pda(vault_num, token_account))
+        .unwrap();
+    assert_eq!(vault_exposure.high_exposure, 100);
+
+    let _ = setup.initialize_market(other_token_account);
+
+    // Success pattern (Never started)
+    let success_res = setup.close_market(other_token_account);
+    assert!(success_res.is_ok());
+
+    let market_res: Result<zone::Market, _> = setup
+        .program
+        .account(setup.get_market_pda(other_token_account));
+    assert!(market_res.is_err());
+}
+
+#[test]
+fn test_settle_prediction() {
+    let token_account = Pubkey::new_unique();
+    let other_token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 13;
+    let end = Utc::now() + chrono::Duration::seconds(5);
+
+    let _ = setup.initialize(vault_num);
+    for token_account in [token_account, other_token_account] {
+        let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+        let _ = setup.start_market(token_account, end);
+        let _ = setup.create_prediction(vault_num, token_account);
+    }
+
+    sleep(std::time::Duration::from_secs(6));
+
+    for token_account in [token_account, other_token_account] {
+        let _ = setup.update_price_feed(token_account, 110_000, -5);
+        let _ = setup.resolve_market(token_account);
+    }
+
+    // Fail pattern (Market of another prediction)
+    let fail_res = setup
+        .program
+        .request()
+        .accounts(zone::accounts::SettlePrediction {
+            prediction: setup.get_prediction_pda(token_account),
+            vault_exposure: setup.get_vault_exposure_pda(vault_num, other_token_account),
+            user: setup.payer.pubkey(),
+            market: setup.get_market_pda(other_token_account),
+            vault: setup.get_vault_pda(vault_num),
+        })
+        .args(zone::instruction::SettlePrediction {})
+        .send();
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ErrorCode::ConstraintHasOne.into())
+    );
+
+    // Fail pattern (Not the user of the prediction)
+    let other = Keypair::new();
+    let fail_res = setup
+        .program
+        .request()
+        .accounts(zone::accounts::SettlePrediction {
+            prediction: setup.get_prediction_pda(token_account),
+            vault_exposure: setup.get_vault_exposure_pda(vault_num, token_account),
+            user: other.pubkey(),
+            market: setup.get_market_pda(token_account),
+            vault: setup.get_vault_pda(vault_num),
+        })
+        .args(zone::instruction::SettlePrediction {})
+        .signer(&other)
+        .send();
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ErrorCode::ConstraintHasOne.into())
+    );
+
+    // Success pattern
+    let success_res = setup.settle_prediction(vault_num, token_account);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already settled)
+    let fail_res = setup.settle_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::PredictionClosed.into())
+    );
+}
+
+#[test]
+fn test_settle_winning_prediction() {
+    let token_account = Pubkey::new_unique();
+    let losing_token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 10;
+    let end = Utc::now() + chrono::Duration::seconds(5);
+
+    let _ = setup.initialize(vault_num);
+    for token_account in [token_account, losing_token_account] {
+        let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+        let _ = setup.start_market(token_account, end);
+        let _ = setup.create_prediction(vault_num, token_account);
+    }
+
+    sleep(std::time::Duration::from_secs(6));
+
+    let _ = setup.update_price_feed(token_account, 110_000, -5);
+    let _ = setup.update_price_feed(losing_token_account, 90_000, -5);
+
+    // Fail pattern (Not resolved yet)
+    let fail_res = setup.settle_winning_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NotResolved.into())
+    );
+
+    let _ = setup.resolve_market(token_account);
+    let _ = setup.resolve_market(losing_token_account);
+
+    // Fail pattern (Prediction lost)
+    let fail_res = setup.settle_winning_prediction(vault_num, losing_token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::PredictionLost.into())
+    );
+
+    // Success pattern
+    let success_res = setup.settle_winning_prediction(vault_num, token_account);
+    assert!(success_res.is_ok());
+
+    // Fail pattern (Already settled)
+    let fail_res = setup.settle_winning_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::PredictionClosed.into())
+    );
+}
+
+#[test]
+fn test_settle_losing_prediction() {
+    let token_account = Pubkey::new_unique();
+    let winning_token_account = Pubkey::new_unique();
+    let setup = TestSetup::new();
+    let vault_num = 17;
+    let end = Utc::now() + chrono::Duration::seconds(5);
+
+    let _ = setup.initialize(vault_num);
+    for token_account in [token_account, winning_token_account] {
+        let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+        let _ = setup.start_market(token_account, end);
+        let _ = setup.create_prediction(vault_num, token_account);
+    }
+
+    // Fail pattern (Not finished)
+    let fail_res = setup.settle_losing_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::NotFinished.into())
+    );
+
+    sleep(std::time::Duration::from_secs(6));
+
+    let _ = setup.update_price_feed(token_account, 90_000, -5);
+    let _ = setup.update_price_feed(winning_token_account, 110_000, -5);
+    let _ = setup.resolve_market(token_account);
+    let _ = setup.resolve_market(winning_token_account);
+
+    // Fail pattern (Prediction won)
+    let fail_res = setup.settle_losing_prediction(vault_num, winning_token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::PredictionWon.into())
+    );
+
+    let vault_before: zone::Vault = setup
+        .program
+        .account(setup.get_vault_pda(vault_num))
+        .unwrap();
+
+    // Success pattern
+    let success_res = setup.settle_losing_prediction(vault_num, token_account);
+    assert!(success_res.is_ok());
+
+    let prediction: zone::Prediction = setup
+        .program
+        .account(setup.get_prediction_pda(token_account))
+        .unwrap();
+    assert!(prediction.status == zone::PredictionStatus::Settled);
+
+    let market: zone::Market = setup
+        .program
+        .account(setup.get_market_pda(token_account))
+        .unwrap();
+    assert_eq!(market.liability, 0);
+    assert_eq!(market.open_predictions, 0);
+
+    let vault: zone::Vault = setup
+        .program
+        .account(setup.get_vault_pda(vault_num))
+        .unwrap();
+    assert_eq!(
+        vault.liabilities,
+        vault_before.liabilities - prediction.payout()
+    );
+    assert_eq!(
+        vault.realized_pnl,
+        vault_before.realized_pnl + prediction.amount as i64
+    );
+
+    // Fail pattern (Already settled)
+    let fail_res = setup.settle_losing_prediction(vault_num, token_account);
+    assert_eq!(
+        error_code(&fail_res),
+        Some(ZoneErrorCode::PredictionClosed.into())
+    );
+}
+
+#[test]
+fn test_keeper() {
+    let token_account = Pubkey::new_unique();
+    let user = Keypair::new();
+    let setup = TestSetup::new();
+    let vault_num = 18;
+    let market_pda = setup.get_market_pda(token_account);
+
+    let prices = std::env::temp_dir().join(format!("zone-keeper-{token_account}.toml"));
+    let set_price =
+        |price: &str| std::fs::write(&prices, format!("{token_account} = \"{price}\"\n")).unwrap();
+
+    let config = client::config::Config {
+        cluster: Cluster::Localnet,
+        program_id: setup.program_id,
+        payer: read_keypair_file(std::env::var("ANCHOR_WALLET").unwrap()).unwrap(),
+        commitment: CommitmentConfig::confirmed(),
+        output: client::output::OutputFormat::Text,
+        simulate: false,
+        priority_fee: None,
+        compute_limit: None,
+        price_source: client::price_source::PriceSourceKind::File(prices.clone()),
+    };
+    let keeper_client = Client::new_with_options(
+        Cluster::Localnet,
+        &config.payer,
+        CommitmentConfig::confirmed(),
+    );
+    let program = keeper_client.program(setup.program_id).unwrap();
+    let keeper = client::keeper::Keeper {
+        program: &program,
+        config: &config,
+        duration: Some(5),
+        market: Some(market_pda),
+        batch_size: 5,
+        retries: 1,
+    };
+
+    let _ = setup.initialize(vault_num);
+    let _ = setup.initialize_market_with_oracle(token_account, 100_000);
+    set_price("1.00000");
+
+    // Starts the market, publishing the price of the file first
+    keeper.poll().unwrap();
+
+    let market: zone::Market = setup.program.account(market_pda).unwrap();
+    assert!(market.started);
+
+    let _ = setup.fund(user.pubkey());
+    let _ = setup.bet(vault_num, token_account, false, None, 100, 0);
+    let _ = setup.create_prediction_as(&user, vault_num, token_account);
+
+    sleep(std::time::Duration::from_secs(6));
+    set_price("1.10000");
+
+    // Resolves the market, which is final without a dispute period
+    keeper.poll().unwrap();
+
+    let market: zone::Market = setup.program.account(market_pda).unwrap();
+    assert!(market.resolved && market.finalized);
+    assert_eq!(market.final_price.mantissa, 110_000);
+    assert_eq!(market.open_predictions, 2);
+
+    // Settles the winning and the losing prediction
+    keeper.poll().unwrap();
+
+    let market: zone::Market = setup.program.account(market_pda).unwrap();
+    assert_eq!(market.open_predictions, 0);
+    assert_eq!(market.liability, 0);
+
+    let user_prediction_pda = setup.get_user_prediction_pda(token_account, user.pubkey());
+    for prediction_pda in [setup.get_prediction_pda(token_account), user_prediction_pda] {
+        let prediction: zone::Prediction = setup.program.account(prediction_pda).unwrap();
+        assert!(prediction.status == zone::PredictionStatus::Settled);
+    }
+
+    let _ = std::fs::remove_file(&prices);
 }